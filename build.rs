@@ -0,0 +1,48 @@
+// Generates the table of shader sources that `shader::embedded` compiles into the
+// binary, so it runs no matter what the working directory is.
+
+use std::{
+    env,
+    fs,
+    path::{Path, PathBuf},
+};
+
+const SHADER_DIR: &str = "shaders";
+
+fn collect(dir: &Path, files: &mut Vec<PathBuf>) {
+    println!("cargo:rerun-if-changed={}", dir.display());
+    let mut entries: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect(),
+        Err(_) => return,
+    };
+    // Sorted, so the generated file doesn't change between builds for no reason
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect(&path, files);
+        } else {
+            println!("cargo:rerun-if-changed={}", path.display());
+            files.push(path);
+        }
+    }
+}
+
+fn main() {
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let mut files = vec![];
+    collect(&manifest_dir.join(SHADER_DIR), &mut files);
+
+    let mut table = String::from("&[\n");
+    for path in &files {
+        // Keys always use forward slashes, relative to the repository root
+        let key: Vec<_> = path.strip_prefix(&manifest_dir).unwrap()
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        table.push_str(&format!("    ({:?}, include_str!({:?})),\n", key.join("/"), path));
+    }
+    table.push_str("]\n");
+
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap()).join("embedded_shaders.rs");
+    fs::write(out_path, table).unwrap();
+}
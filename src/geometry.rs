@@ -0,0 +1,164 @@
+// Triangle meshes on the CPU, the common representation every model loader
+// produces and every mesh-processing step works on.
+
+use crate::mesh::{AttributeData, Mesh, MeshError, VertexAttribute, VertexLayout};
+
+pub mod normals;
+pub mod optimize;
+pub mod primitives;
+pub mod tangents;
+
+/// An indexed triangle mesh. Every attribute other than `positions` is either empty
+/// or has exactly one entry per position.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MeshData {
+    pub name: String,
+    pub positions: Vec<glm::Vec3>,
+    pub normals: Vec<glm::Vec3>,
+    pub uvs: Vec<glm::Vec2>,
+    pub colors: Vec<glm::Vec4>,
+    /// `xyz` is the tangent, `w` the handedness of the bitangent.
+    pub tangents: Vec<glm::Vec4>,
+    /// Three per triangle, counter-clockwise when seen from the front.
+    pub indices: Vec<u32>,
+    /// Index into the materials of the `Model` this mesh was loaded with.
+    pub material: Option<usize>,
+}
+
+fn flatten<const N: usize>(vectors: &[glm::TVec<f32, N>]) -> Vec<f32> {
+    vectors.iter().flat_map(|vector| vector.iter().copied()).collect()
+}
+
+/// The bit patterns of the components of `vector`, for hashing and exact comparison.
+pub(crate) fn bits<const N: usize>(vector: &glm::TVec<f32, N>) -> [u32; N] {
+    // Adding zero turns -0.0 into 0.0, so they compare equal too
+    std::array::from_fn(|index| (vector[index] + 0.0).to_bits())
+}
+
+impl MeshData {
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    pub fn triangles(&self) -> impl Iterator<Item = [u32; 3]> + '_ {
+        self.indices.chunks_exact(3).map(|triangle| [triangle[0], triangle[1], triangle[2]])
+    }
+
+    /// An empty mesh with the same name and material as this one.
+    pub fn empty_like(&self) -> MeshData {
+        MeshData { name: self.name.clone(), material: self.material, ..MeshData::default() }
+    }
+
+    /// Append vertex `index` of `source`, with every attribute `source` has, and
+    /// return its index in this mesh.
+    pub fn push_vertex_from(&mut self, source: &MeshData, index: usize) -> u32 {
+        self.positions.push(source.positions[index]);
+        if !source.normals.is_empty() {
+            self.normals.push(source.normals[index]);
+        }
+        if !source.uvs.is_empty() {
+            self.uvs.push(source.uvs[index]);
+        }
+        if !source.colors.is_empty() {
+            self.colors.push(source.colors[index]);
+        }
+        if !source.tangents.is_empty() {
+            self.tangents.push(source.tangents[index]);
+        }
+        (self.positions.len() - 1) as u32
+    }
+
+    /// A copy with `transform` applied to positions, normals and tangents. Mirroring
+    /// transforms also reverse the winding, so front faces stay counter-clockwise.
+    pub fn transformed(&self, transform: &glm::Mat4) -> MeshData {
+        let linear = glm::mat4_to_mat3(transform);
+        let normal_matrix = glm::inverse_transpose(linear);
+        let mirrored = linear.determinant() < 0.0;
+        let mut mesh = self.clone();
+        for position in &mut mesh.positions {
+            *position = (transform * position.push(1.0)).xyz();
+        }
+        for normal in &mut mesh.normals {
+            *normal = (normal_matrix * *normal).normalize();
+        }
+        for tangent in &mut mesh.tangents {
+            let direction = (linear * tangent.xyz()).normalize();
+            let handedness = if mirrored { -tangent.w } else { tangent.w };
+            *tangent = direction.push(handedness);
+        }
+        if mirrored {
+            for triangle in mesh.indices.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+        mesh
+    }
+
+    /// Append the vertices and triangles of `other`. Attributes only one of the meshes
+    /// has are dropped, and the material is kept only if both use the same one.
+    pub fn append(&mut self, other: &MeshData) {
+        if other.positions.is_empty() {
+            return;
+        }
+        let first = self.positions.is_empty();
+        fn merge<T: Copy>(own: &mut Vec<T>, other: &[T], first: bool) {
+            if first || (!own.is_empty() && !other.is_empty()) {
+                own.extend_from_slice(other);
+            } else {
+                own.clear();
+            }
+        }
+        let offset = self.positions.len() as u32;
+        merge(&mut self.normals, &other.normals, first);
+        merge(&mut self.uvs, &other.uvs, first);
+        merge(&mut self.colors, &other.colors, first);
+        merge(&mut self.tangents, &other.tangents, first);
+        self.positions.extend_from_slice(&other.positions);
+        self.indices.extend(other.indices.iter().map(|index| index + offset));
+        if first {
+            self.material = other.material;
+        } else if self.material != other.material {
+            self.material = None;
+        }
+    }
+
+    /// An interleaved layout with the standard attribute for every attribute this mesh has.
+    pub fn layout(&self) -> VertexLayout {
+        let mut layout = VertexLayout::interleaved().with(VertexAttribute::position());
+        if !self.normals.is_empty() {
+            layout = layout.with(VertexAttribute::normal());
+        }
+        if !self.uvs.is_empty() {
+            layout = layout.with(VertexAttribute::uv());
+        }
+        if !self.tangents.is_empty() {
+            layout = layout.with(VertexAttribute::tangent());
+        }
+        if !self.colors.is_empty() {
+            layout = layout.with(VertexAttribute::color());
+        }
+        layout
+    }
+
+    /// Upload to the GPU with `layout()`.
+    pub unsafe fn upload(&self) -> Result<Mesh, MeshError> {
+        let mut data: Vec<AttributeData> = vec![(&flatten(&self.positions)).into()];
+        if !self.normals.is_empty() {
+            data.push((&flatten(&self.normals)).into());
+        }
+        if !self.uvs.is_empty() {
+            data.push((&flatten(&self.uvs)).into());
+        }
+        if !self.tangents.is_empty() {
+            data.push((&flatten(&self.tangents)).into());
+        }
+        if !self.colors.is_empty() {
+            data.push((&flatten(&self.colors)).into());
+        }
+        Mesh::new(&self.layout(), &data, Some(&self.indices))
+    }
+}
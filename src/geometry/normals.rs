@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use super::{bits, MeshData};
+
+/// The unit normal of the triangle `a, b, c`, facing the side it is counter-clockwise
+/// from. Zero for degenerate triangles.
+pub fn face_normal(a: &glm::Vec3, b: &glm::Vec3, c: &glm::Vec3) -> glm::Vec3 {
+    let normal = (b - a).cross(&(c - a));
+    let length = normal.norm();
+    if length > 0.0 { normal / length } else { glm::zero() }
+}
+
+/// The angle at each corner of the triangle `a, b, c`, or zero if it is degenerate.
+fn corner_angles(a: &glm::Vec3, b: &glm::Vec3, c: &glm::Vec3) -> [f32; 3] {
+    let angle = |corner: &glm::Vec3, next: &glm::Vec3, previous: &glm::Vec3| {
+        let (to_next, to_previous) = (next - corner, previous - corner);
+        if to_next.norm() > 0.0 && to_previous.norm() > 0.0 {
+            glm::angle(&to_next, &to_previous)
+        } else {
+            0.0
+        }
+    };
+    [angle(a, b, c), angle(b, c, a), angle(c, a, b)]
+}
+
+/// A copy of `mesh` where every triangle has the normal of its plane, replacing any
+/// normals it had. Vertices are split where triangles with different normals meet,
+/// but stay shared between triangles with exactly the same normal, such as the two
+/// halves of a quad.
+pub fn flat(mesh: &MeshData) -> MeshData {
+    let faces: Vec<glm::Vec3> = mesh.triangles()
+        .map(|triangle| {
+            let [a, b, c] = triangle.map(|index| mesh.positions[index as usize]);
+            face_normal(&a, &b, &c)
+        })
+        .collect();
+    with_corner_normals(mesh, |triangle, _| faces[triangle])
+}
+
+/// A copy of `mesh` where every corner has an average of the normals of the triangles
+/// around its position, weighted by their angle at that corner, replacing any normals
+/// it had. Triangles only count if their normal is within `crease_angle` (in radians)
+/// of the corner's own triangle, so edges sharper than that stay hard.
+///
+/// Corners are grouped by position rather than by vertex, so normals are smooth across
+/// seams where vertices are split for other attributes, such as texture coordinates.
+pub fn smooth(mesh: &MeshData, crease_angle: f32) -> MeshData {
+    let threshold = crease_angle.cos();
+    let faces: Vec<(glm::Vec3, [f32; 3])> = mesh.triangles()
+        .map(|triangle| {
+            let [a, b, c] = triangle.map(|index| mesh.positions[index as usize]);
+            (face_normal(&a, &b, &c), corner_angles(&a, &b, &c))
+        })
+        .collect();
+    // The corners at every position, as (triangle, corner)
+    let mut corners: HashMap<[u32; 3], Vec<(usize, usize)>> = HashMap::new();
+    for (triangle, indices) in mesh.triangles().enumerate() {
+        for (corner, index) in indices.iter().enumerate() {
+            corners.entry(bits(&mesh.positions[*index as usize])).or_default().push((triangle, corner));
+        }
+    }
+
+    with_corner_normals(mesh, |triangle, corner| {
+        let own = faces[triangle].0;
+        let position = mesh.positions[mesh.indices[triangle * 3 + corner] as usize];
+        let mut normal = glm::Vec3::zeros();
+        for &(other, other_corner) in &corners[&bits(&position)] {
+            let (face, angles) = faces[other];
+            // Degenerate triangles take the normals of everything around them
+            if own == glm::Vec3::zeros() || face.dot(&own) >= threshold {
+                normal += face * angles[other_corner];
+            }
+        }
+        if normal.norm() > 0.0 { normal.normalize() } else { own }
+    })
+}
+
+/// A copy of `mesh` with `normal(triangle, corner)` at every corner of every triangle.
+/// Vertices are split where corners with different normals meet, and stay shared where
+/// they have exactly the same one.
+fn with_corner_normals(mesh: &MeshData, normal: impl Fn(usize, usize) -> glm::Vec3) -> MeshData {
+    let mut result = mesh.empty_like();
+    let mut split: HashMap<(u32, [u32; 3]), u32> = HashMap::new();
+    for (triangle, indices) in mesh.triangles().enumerate() {
+        for (corner, index) in indices.iter().enumerate() {
+            let normal = normal(triangle, corner);
+            let vertex = *split.entry((*index, bits(&normal))).or_insert_with(|| {
+                let vertex = result.push_vertex_from(mesh, *index as usize);
+                result.normals.truncate(vertex as usize);
+                result.normals.push(normal);
+                vertex
+            });
+            result.indices.push(vertex);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::primitives;
+
+    use super::*;
+
+    #[test]
+    fn flat_normals_share_coplanar_vertices() {
+        let mut quad = primitives::plane(1.0, 1.0, 1, 1);
+        quad.normals.clear();
+        let mesh = flat(&quad);
+        assert_eq!(mesh.vertex_count(), 4);
+        assert_eq!(mesh.normals, vec![glm::vec3(0.0, 1.0, 0.0); 4]);
+    }
+
+    #[test]
+    fn crease_angle_keeps_box_edges_hard() {
+        let cube = primitives::cuboid(glm::vec3(2.0, 2.0, 2.0), 1);
+        let hard = smooth(&cube, 30.0f32.to_radians());
+        assert_eq!(hard.vertex_count(), 24);
+        assert_eq!(hard.normals, cube.normals);
+
+        // Every corner sees three faces with a right angle each
+        let soft = smooth(&cube, 100.0f32.to_radians());
+        assert_eq!(soft.vertex_count(), 24);
+        for (position, normal) in soft.positions.iter().zip(&soft.normals) {
+            assert!(glm::distance(&position.normalize(), normal) < 1e-5);
+        }
+    }
+
+    #[test]
+    fn smooth_normals_are_weighted_by_angle() {
+        // Two triangles on the floor and one on a wall meet at the origin. The floor
+        // is split in two, but has the same angle there as the wall, so both count
+        // the same.
+        let mesh = MeshData {
+            positions: vec![
+                glm::vec3(0.0, 0.0, 0.0), glm::vec3(1.0, 0.0, 0.0), glm::vec3(1.0, 0.0, -1.0),
+                glm::vec3(0.0, 0.0, -1.0), glm::vec3(0.0, 1.0, 0.0),
+            ],
+            indices: vec![0, 1, 2, 0, 2, 3, 0, 4, 1],
+            ..MeshData::default()
+        };
+        let smooth = smooth(&mesh, std::f32::consts::PI);
+        let expected = glm::vec3(0.0, 1.0, -1.0).normalize();
+        assert!(glm::distance(&smooth.normals[smooth.indices[0] as usize], &expected) < 1e-5);
+    }
+
+    #[test]
+    fn smooth_normals_cross_texture_seams() {
+        let sphere = primitives::uv_sphere(1.0, 12, 6);
+        let mesh = smooth(&sphere, 60.0f32.to_radians());
+        assert_eq!(mesh.triangle_count(), sphere.triangle_count());
+        for (position, normal) in mesh.positions.iter().zip(&mesh.normals) {
+            assert!(normal.dot(position) > 0.99, "{} {}", position, normal);
+        }
+    }
+}
@@ -0,0 +1,400 @@
+// Cleaning up meshes and reordering them so the GPU draws them faster.
+//
+// `optimize` runs every step in order, but each is also usable on its own.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use super::{normals, MeshData};
+
+/// Welds `mesh` with `epsilon`, removes degenerate triangles, then reorders triangles for
+/// the vertex cache and vertices for fetching, and reports how the mesh changed.
+pub fn optimize(mesh: &MeshData, epsilon: f32) -> (MeshData, Report) {
+    let before = Statistics::of(mesh);
+    let welded = weld(mesh, epsilon);
+    let cleaned = remove_degenerate_triangles(&welded);
+    let optimized = optimize_vertex_fetch(&optimize_vertex_cache(&cleaned));
+    let after = Statistics::of(&optimized);
+    (optimized, Report { before, after })
+}
+
+/// Whether every attribute of vertex `a` is within `epsilon` of vertex `b`, in every component.
+fn within(mesh: &MeshData, a: usize, b: usize, epsilon: f32) -> bool {
+    fn close<const N: usize>(values: &[glm::TVec<f32, N>], a: usize, b: usize, epsilon: f32) -> bool {
+        values.is_empty() || (values[a] - values[b]).iter().all(|difference| difference.abs() <= epsilon)
+    }
+    close(&mesh.positions, a, b, epsilon)
+        && close(&mesh.normals, a, b, epsilon)
+        && close(&mesh.uvs, a, b, epsilon)
+        && close(&mesh.colors, a, b, epsilon)
+        && close(&mesh.tangents, a, b, epsilon)
+}
+
+/// Merge vertices where every attribute is within `epsilon` of another vertex's. Each
+/// merged vertex keeps the attributes of the first of them in the mesh, and vertices no
+/// triangle uses are kept. With an `epsilon` of zero, only exact duplicates are merged.
+pub fn weld(mesh: &MeshData, epsilon: f32) -> MeshData {
+    // Vertices are bucketed by position, so only those in neighbouring cells are compared
+    let cell_size = if epsilon > 0.0 { epsilon } else { 1.0 };
+    let cell = |position: &glm::Vec3| (position / cell_size).map(|value| value.floor() as i64);
+    let mut cells: HashMap<glm::TVec3<i64>, Vec<usize>> = HashMap::new();
+
+    let mut result = mesh.empty_like();
+    let mut remap = vec![0u32; mesh.vertex_count()];
+    for vertex in 0..mesh.vertex_count() {
+        let home = cell(&mesh.positions[vertex]);
+        let mut existing = None;
+        'search: for x in -1..=1 {
+            for y in -1..=1 {
+                for z in -1..=1 {
+                    let Some(candidates) = cells.get(&(home + glm::vec3(x, y, z))) else {
+                        continue;
+                    };
+                    if let Some(&candidate) = candidates.iter().find(|&&candidate| within(mesh, candidate, vertex, epsilon)) {
+                        existing = Some(remap[candidate]);
+                        break 'search;
+                    }
+                }
+            }
+        }
+        remap[vertex] = existing.unwrap_or_else(|| {
+            cells.entry(home).or_default().push(vertex);
+            result.push_vertex_from(mesh, vertex)
+        });
+    }
+    result.indices = mesh.indices.iter().map(|&index| remap[index as usize]).collect();
+    result
+}
+
+/// Remove triangles that use the same vertex twice or have no area. Vertices are kept,
+/// even if no triangle uses them any more.
+pub fn remove_degenerate_triangles(mesh: &MeshData) -> MeshData {
+    let mut result = mesh.clone();
+    result.indices = mesh.triangles()
+        .filter(|&[a, b, c]| {
+            let [p, q, r] = [a, b, c].map(|index| mesh.positions[index as usize]);
+            a != b && b != c && c != a && normals::face_normal(&p, &q, &r) != glm::Vec3::zeros()
+        })
+        .flatten()
+        .collect();
+    result
+}
+
+/// The size of the cache `optimize_vertex_cache` optimizes for.
+const OPTIMIZED_CACHE_SIZE: usize = 32;
+
+/// How much it is worth drawing a triangle with a vertex at `position` in the cache
+/// (most recently used first) that is still used by `remaining` triangles.
+fn vertex_score(position: Option<usize>, remaining: usize) -> f32 {
+    if remaining == 0 {
+        return -1.0;
+    }
+    let cache = match position {
+        // The last triangle's vertices are used no matter the order
+        Some(position) if position < 3 => { 0.75 },
+        Some(position) => {
+            let age = (position - 3) as f32 / (OPTIMIZED_CACHE_SIZE - 3) as f32;
+            (1.0 - age).powf(1.5)
+        },
+        None => { 0.0 },
+    };
+    // Finish off vertices with few triangles left, so they leave the cache for good
+    cache + 2.0 * (remaining as f32).powf(-0.5)
+}
+
+/// Reorder the triangles so that vertices are reused while they are still in the
+/// post-transform vertex cache, with Tom Forsyth's "Linear-Speed Vertex Cache
+/// Optimisation". The vertices and the winding of each triangle are unchanged.
+pub fn optimize_vertex_cache(mesh: &MeshData) -> MeshData {
+    let triangles: Vec<[u32; 3]> = mesh.triangles().collect();
+    let mut remaining = vec![0usize; mesh.vertex_count()];
+    for &index in &mesh.indices {
+        remaining[index as usize] += 1;
+    }
+    // The triangles using each vertex
+    let mut adjacent: Vec<Vec<usize>> = remaining.iter().map(|&count| Vec::with_capacity(count)).collect();
+    for (triangle, indices) in triangles.iter().enumerate() {
+        for &index in indices {
+            adjacent[index as usize].push(triangle);
+        }
+    }
+
+    let mut vertex_scores: Vec<f32> = remaining.iter().map(|&count| vertex_score(None, count)).collect();
+    let triangle_score = |scores: &[f32], triangle: usize| -> f32 {
+        triangles[triangle].iter().map(|&index| scores[index as usize]).sum()
+    };
+    let mut emitted = vec![false; triangles.len()];
+    let mut cache: Vec<u32> = vec![];
+    // Where to look for a triangle to start from when none touch the cache
+    let mut next_unemitted = 0;
+    let mut best = (0..triangles.len()).max_by(|&a, &b| {
+        triangle_score(&vertex_scores, a).total_cmp(&triangle_score(&vertex_scores, b))
+    });
+
+    let mut indices = Vec::with_capacity(mesh.indices.len());
+    while let Some(triangle) = best {
+        emitted[triangle] = true;
+        indices.extend_from_slice(&triangles[triangle]);
+        for &index in &triangles[triangle] {
+            remaining[index as usize] -= 1;
+        }
+
+        // Move the triangle's vertices to the front of the cache
+        let mut touched: Vec<u32> = triangles[triangle].to_vec();
+        touched.extend(cache.iter().filter(|index| !triangles[triangle].contains(index)));
+        cache = touched.clone();
+        cache.truncate(OPTIMIZED_CACHE_SIZE);
+        for (position, &index) in touched.iter().enumerate() {
+            let position = Some(position).filter(|&position| position < OPTIMIZED_CACHE_SIZE);
+            vertex_scores[index as usize] = vertex_score(position, remaining[index as usize]);
+        }
+
+        // The best triangle is almost always one using a vertex in the cache
+        best = None;
+        let mut best_score = f32::MIN;
+        for &index in &touched {
+            for &candidate in &adjacent[index as usize] {
+                let score = triangle_score(&vertex_scores, candidate);
+                if !emitted[candidate] && score > best_score {
+                    best = Some(candidate);
+                    best_score = score;
+                }
+            }
+        }
+        if best.is_none() {
+            while next_unemitted < triangles.len() && emitted[next_unemitted] {
+                next_unemitted += 1;
+            }
+            best = Some(next_unemitted).filter(|&triangle| triangle < triangles.len());
+        }
+    }
+
+    MeshData { indices, ..mesh.clone() }
+}
+
+/// Reorder the vertices in the order the triangles first use them, so vertices drawn
+/// together are close together in memory. Vertices no triangle uses are removed.
+pub fn optimize_vertex_fetch(mesh: &MeshData) -> MeshData {
+    let mut result = mesh.empty_like();
+    let mut remap: Vec<Option<u32>> = vec![None; mesh.vertex_count()];
+    for &index in &mesh.indices {
+        let vertex = *remap[index as usize].get_or_insert_with(|| result.push_vertex_from(mesh, index as usize));
+        result.indices.push(vertex);
+    }
+    result
+}
+
+/// The size of the FIFO vertex cache `Statistics` simulates, a common size in hardware.
+const SIMULATED_CACHE_SIZE: usize = 16;
+/// The cache line size and number of lines of the memory cache `Statistics` simulates.
+const CACHE_LINE_SIZE: usize = 64;
+const CACHE_LINES: usize = 64;
+
+/// How well a mesh suits the caches used while drawing it, from simulating them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Statistics {
+    pub vertices: usize,
+    pub triangles: usize,
+    /// Vertices no triangle uses.
+    pub unused_vertices: usize,
+    /// Triangles that use the same vertex twice or have no area.
+    pub degenerate_triangles: usize,
+    /// Average cache miss ratio: vertices transformed per triangle. From 0.5 at best
+    /// on large regular meshes up to 3.
+    pub acmr: f32,
+    /// Average transformed vertex ratio: how many times each used vertex is transformed.
+    /// 1 at best.
+    pub atvr: f32,
+    /// Bytes of vertex data read from memory, relative to the size of the used vertices.
+    /// 1 at best.
+    pub overfetch: f32,
+}
+
+impl Statistics {
+    pub fn of(mesh: &MeshData) -> Statistics {
+        let mut used = vec![false; mesh.vertex_count()];
+        let mut cache: Vec<u32> = Vec::with_capacity(SIMULATED_CACHE_SIZE);
+        let mut transformed = 0;
+        let stride = mesh.layout().offsets().1;
+        let mut lines: Vec<usize> = Vec::with_capacity(CACHE_LINES);
+        let mut fetched_lines = 0;
+        for &index in &mesh.indices {
+            used[index as usize] = true;
+            if cache.contains(&index) {
+                continue;
+            }
+            // A vertex cache miss: the vertex is transformed, reading it from memory
+            transformed += 1;
+            if cache.len() == SIMULATED_CACHE_SIZE {
+                cache.remove(0);
+            }
+            cache.push(index);
+            let start = index as usize * stride;
+            for line in start / CACHE_LINE_SIZE..=(start + stride - 1) / CACHE_LINE_SIZE {
+                if !lines.contains(&line) {
+                    fetched_lines += 1;
+                    if lines.len() == CACHE_LINES {
+                        lines.remove(0);
+                    }
+                    lines.push(line);
+                }
+            }
+        }
+
+        let used_vertices = used.iter().filter(|&&used| used).count();
+        let ratio = |value: usize, total: usize| if total > 0 { value as f32 / total as f32 } else { 0.0 };
+        let degenerate = mesh.triangle_count() - remove_degenerate_triangles(mesh).triangle_count();
+        Statistics {
+            vertices: mesh.vertex_count(),
+            triangles: mesh.triangle_count(),
+            unused_vertices: mesh.vertex_count() - used_vertices,
+            degenerate_triangles: degenerate,
+            acmr: ratio(transformed, mesh.triangle_count()),
+            atvr: ratio(transformed, used_vertices),
+            overfetch: ratio(fetched_lines * CACHE_LINE_SIZE, used_vertices * stride),
+        }
+    }
+}
+
+/// The statistics of a mesh before and after `optimize`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Report {
+    pub before: Statistics,
+    pub after: Statistics,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (before, after) = (&self.before, &self.after);
+        writeln!(f, "                      before     after")?;
+        writeln!(f, "vertices          {:>10} {:>9}", before.vertices, after.vertices)?;
+        writeln!(f, "triangles         {:>10} {:>9}", before.triangles, after.triangles)?;
+        writeln!(f, "unused vertices   {:>10} {:>9}", before.unused_vertices, after.unused_vertices)?;
+        writeln!(f, "degenerate        {:>10} {:>9}", before.degenerate_triangles, after.degenerate_triangles)?;
+        writeln!(f, "ACMR              {:>10.3} {:>9.3}", before.acmr, after.acmr)?;
+        writeln!(f, "ATVR              {:>10.3} {:>9.3}", before.atvr, after.atvr)?;
+        write!(f, "overfetch         {:>10.3} {:>9.3}", before.overfetch, after.overfetch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+    use crate::geometry::{bits, primitives};
+
+    use super::*;
+
+    /// The triangles of `mesh` by the positions of their corners, sorted so they can be
+    /// compared regardless of order, with each rotated to start at its smallest corner.
+    fn triangle_positions(mesh: &MeshData) -> Vec<[[u32; 3]; 3]> {
+        let mut triangles: Vec<[[u32; 3]; 3]> = mesh.triangles()
+            .map(|triangle| {
+                let mut corners = triangle.map(|index| bits(&mesh.positions[index as usize]));
+                let smallest = (0..3).min_by_key(|&corner| corners[corner]).unwrap();
+                corners.rotate_left(smallest);
+                corners
+            })
+            .collect();
+        triangles.sort_unstable();
+        triangles
+    }
+
+    /// Every triangle with its own vertices, like a mesh loaded from STL before
+    /// de-duplication, with the triangles shuffled.
+    fn soup(mesh: &MeshData) -> MeshData {
+        let mut triangles: Vec<[u32; 3]> = mesh.triangles().collect();
+        triangles.shuffle(&mut StdRng::seed_from_u64(25));
+        let mut soup = mesh.empty_like();
+        for triangle in triangles {
+            for index in triangle {
+                let vertex = soup.push_vertex_from(mesh, index as usize);
+                soup.indices.push(vertex);
+            }
+        }
+        soup
+    }
+
+    #[test]
+    fn weld_merges_nearby_vertices_only() {
+        let mut mesh = soup(&primitives::plane(1.0, 1.0, 4, 4));
+        mesh.positions[0].x += 1e-5;
+        assert_eq!(weld(&mesh, 1e-4).vertex_count(), 25);
+        assert_eq!(weld(&mesh, 0.0).vertex_count(), 26);
+
+        // Vertices at the same position with different UVs stay apart
+        mesh.uvs[0].y += 0.5;
+        assert_eq!(weld(&mesh, 1e-4).vertex_count(), 26);
+    }
+
+    #[test]
+    fn removes_degenerate_triangles() {
+        let mesh = MeshData {
+            positions: vec![
+                glm::vec3(0.0, 0.0, 0.0), glm::vec3(1.0, 0.0, 0.0), glm::vec3(0.0, 1.0, 0.0),
+                glm::vec3(1.0, 0.0, 0.0),
+            ],
+            indices: vec![0, 1, 2, 0, 0, 2, 1, 3, 2],
+            ..MeshData::default()
+        };
+        assert_eq!(Statistics::of(&mesh).degenerate_triangles, 2);
+        assert_eq!(remove_degenerate_triangles(&mesh).indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn vertex_cache_order_keeps_triangles_and_transforms_fewer_vertices() {
+        let sphere = primitives::uv_sphere(1.0, 48, 24);
+        let shuffled = weld(&soup(&sphere), 0.0);
+        let optimized = optimize_vertex_cache(&shuffled);
+
+        assert_eq!(triangle_positions(&optimized), triangle_positions(&shuffled));
+        let (before, after) = (Statistics::of(&shuffled), Statistics::of(&optimized));
+        assert!(after.acmr < 0.8 && after.acmr < before.acmr * 0.5, "ACMR went from {} to {}", before.acmr, after.acmr);
+    }
+
+    #[test]
+    fn vertex_fetch_order_follows_first_use() {
+        let mut mesh = optimize_vertex_cache(&primitives::uv_sphere(1.0, 48, 24));
+        // Shuffle the vertices, and add another one no triangle uses
+        let mut order: Vec<usize> = (0..mesh.vertex_count()).collect();
+        order.shuffle(&mut StdRng::seed_from_u64(25));
+        let mut shuffled = mesh.empty_like();
+        let mut remap = vec![0; mesh.vertex_count()];
+        for vertex in order {
+            remap[vertex] = shuffled.push_vertex_from(&mesh, vertex);
+        }
+        shuffled.indices = mesh.indices.iter().map(|&index| remap[index as usize]).collect();
+        shuffled.push_vertex_from(&mesh.clone(), 0);
+        mesh = shuffled;
+        let optimized = optimize_vertex_fetch(&mesh);
+
+        assert_eq!(triangle_positions(&optimized), triangle_positions(&mesh));
+        assert_eq!(optimized.vertex_count(), mesh.vertex_count() - Statistics::of(&mesh).unused_vertices);
+        let mut seen = 0;
+        for &index in &optimized.indices {
+            assert!(index <= seen);
+            seen = seen.max(index + 1);
+        }
+        let (before, after) = (Statistics::of(&mesh), Statistics::of(&optimized));
+        assert_eq!(after.unused_vertices, 0);
+        assert_eq!(after.acmr, before.acmr);
+        assert!(after.overfetch < before.overfetch, "Overfetch went from {} to {}", before.overfetch, after.overfetch);
+    }
+
+    #[test]
+    fn optimize_reports_improvements() {
+        let mut mesh = soup(&primitives::torus(1.0, 0.25, 32, 16));
+        mesh.indices.extend_from_slice(&[0, 0, 1]);
+        let (optimized, report) = optimize(&mesh, 1e-6);
+
+        assert_eq!(report.before.vertices, mesh.vertex_count());
+        assert_eq!(report.before.degenerate_triangles, 1);
+        assert_eq!(report.after, Statistics::of(&optimized));
+        assert_eq!(report.after.degenerate_triangles, 0);
+        assert_eq!(report.after.unused_vertices, 0);
+        // Every vertex of the torus, with the seams still split for the UVs
+        assert_eq!(report.after.vertices, 33 * 17);
+        assert!(report.after.acmr < report.before.acmr);
+        assert!(report.to_string().contains("ACMR"));
+    }
+}
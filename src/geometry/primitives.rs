@@ -0,0 +1,375 @@
+// Generators for common shapes, with positions, normals, UVs and indices.
+//
+// Every shape is centered on the origin with Y up, and has front faces wound
+// counter-clockwise with normals pointing out. UVs run from (0, 0) at the bottom left
+// of a texture to (1, 1) at the top right.
+
+use std::collections::HashMap;
+use std::f32::consts::{PI, TAU};
+
+use super::MeshData;
+
+/// The sine and cosine of `fraction` of a full turn. A whole turn gives exactly the same
+/// as none, so the vertices on both sides of a seam are at the same position.
+fn turn(fraction: f32) -> (f32, f32) {
+    (fraction.fract() * TAU).sin_cos()
+}
+
+/// A grid of `columns` by `rows` quads. `vertex` is called with the UV of every grid
+/// point and returns its position and normal; moving along `u` and then `v` must turn
+/// counter-clockwise around the normal. Triangles that collapse, such as those at the
+/// poles of a sphere where a whole row shares one position, are left out.
+fn grid(name: &str, columns: u32, rows: u32, vertex: impl Fn(f32, f32) -> (glm::Vec3, glm::Vec3)) -> MeshData {
+    let mut mesh = MeshData { name: name.to_string(), ..MeshData::default() };
+    for row in 0..=rows {
+        for column in 0..=columns {
+            let uv = glm::vec2(column as f32 / columns as f32, row as f32 / rows as f32);
+            let (position, normal) = vertex(uv.x, uv.y);
+            mesh.positions.push(position);
+            mesh.normals.push(normal);
+            mesh.uvs.push(uv);
+        }
+    }
+    for row in 0..rows {
+        for column in 0..columns {
+            let a = row * (columns + 1) + column;
+            let d = a + columns + 1;
+            for triangle in [[a, a + 1, d + 1], [a, d + 1, d]] {
+                let [p, q, r] = triangle.map(|index| mesh.positions[index as usize]);
+                if p != q && q != r && r != p {
+                    mesh.indices.extend_from_slice(&triangle);
+                }
+            }
+        }
+    }
+    mesh
+}
+
+/// A `width` by `depth` rectangle in the XZ plane facing up, split into `columns` by
+/// `rows` quads.
+pub fn plane(width: f32, depth: f32, columns: u32, rows: u32) -> MeshData {
+    assert!(columns > 0 && rows > 0, "A plane needs at least one column and row");
+    grid("plane", columns, rows, |u, v| {
+        (glm::vec3((u - 0.5) * width, 0.0, (0.5 - v) * depth), glm::vec3(0.0, 1.0, 0.0))
+    })
+}
+
+/// A box of the given `size`, with every face split into `segments` by `segments`
+/// quads. Each face has its own vertices and covers the whole texture.
+pub fn cuboid(size: glm::Vec3, segments: u32) -> MeshData {
+    assert!(segments > 0, "A box needs at least one segment per face");
+    let (x, y, z) = (glm::vec3(1.0, 0.0, 0.0), glm::vec3(0.0, 1.0, 0.0), glm::vec3(0.0, 0.0, 1.0));
+    // The normal and the directions of u and v on every face
+    let faces = [(x, -z, y), (-x, z, y), (y, x, -z), (-y, x, z), (z, x, y), (-z, -x, y)];
+    let mut mesh = MeshData { name: "box".to_string(), ..MeshData::default() };
+    for (normal, u_axis, v_axis) in faces {
+        mesh.append(&grid("", segments, segments, |u, v| {
+            let position = normal * 0.5 + u_axis * (u - 0.5) + v_axis * (v - 0.5);
+            (position.component_mul(&size), normal)
+        }));
+    }
+    mesh
+}
+
+/// A sphere made of `stacks` rings of latitude, each split into `sectors` quads. The
+/// texture wraps around once, with `v` going from the south pole to the north pole.
+pub fn uv_sphere(radius: f32, sectors: u32, stacks: u32) -> MeshData {
+    assert!(sectors >= 3 && stacks >= 2, "A sphere needs at least 3 sectors and 2 stacks");
+    grid("uv_sphere", sectors, stacks, |u, v| {
+        let (sin_angle, cos_angle) = turn(u);
+        // Exactly zero at the poles, so their triangles collapse
+        let ring = if v == 0.0 || v == 1.0 { 0.0 } else { (v * PI).sin() };
+        let normal = glm::vec3(ring * sin_angle, -(v * PI).cos(), ring * cos_angle);
+        (normal * radius, normal)
+    })
+}
+
+/// A sphere made by splitting each face of an icosahedron into four `subdivisions`
+/// times, so its triangles are all about the same size. Vertices on the seam of the
+/// texture and at the poles are duplicated, so there are slightly more than
+/// `10 * 4^subdivisions + 2`.
+pub fn icosphere(radius: f32, subdivisions: u32) -> MeshData {
+    // A vertex at each pole, and two rings of five between them. The rings are turned
+    // so that no vertex of the icosahedron is on the seam.
+    let mut directions = vec![glm::vec3(0.0, 1.0, 0.0), glm::vec3(0.0, -1.0, 0.0)];
+    let (height, ring) = (1.0 / 5.0f32.sqrt(), 2.0 / 5.0f32.sqrt());
+    for (offset, y) in [(0.05, height), (0.15, -height)] {
+        for index in 0..5 {
+            let (sin_angle, cos_angle) = turn(offset + index as f32 / 5.0);
+            directions.push(glm::vec3(sin_angle * ring, y, cos_angle * ring));
+        }
+    }
+    let (upper, lower) = (|index: u32| 2 + index % 5, |index: u32| 7 + index % 5);
+    let mut triangles: Vec<[u32; 3]> = (0..5)
+        .flat_map(|index| [
+            [0, upper(index), upper(index + 1)],
+            [upper(index), lower(index), upper(index + 1)],
+            [upper(index + 1), lower(index), lower(index + 1)],
+            [1, lower(index + 1), lower(index)],
+        ])
+        .collect();
+
+    for _ in 0..subdivisions {
+        // The new vertex in the middle of each edge, shared by both its triangles
+        let mut midpoints: HashMap<(u32, u32), u32> = HashMap::new();
+        let mut midpoint = |a: u32, b: u32| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                directions.push((directions[a as usize] + directions[b as usize]).normalize());
+                (directions.len() - 1) as u32
+            })
+        };
+        triangles = triangles.iter()
+            .flat_map(|&[a, b, c]| {
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    let mut mesh = MeshData { name: "icosphere".to_string(), ..MeshData::default() };
+    for direction in &directions {
+        mesh.positions.push(direction * radius);
+        mesh.normals.push(*direction);
+        mesh.uvs.push(glm::vec2(0.5 + direction.x.atan2(direction.z) / TAU, 0.5 + direction.y.asin() / PI));
+    }
+    let source = mesh.clone();
+    // Triangles that cross the seam get copies of the vertices on its left with `u`
+    // past 1, so the texture doesn't wrap backwards across them
+    let mut wrapped: HashMap<u32, u32> = HashMap::new();
+    for triangle in triangles {
+        let u = triangle.map(|index| mesh.uvs[index as usize].x);
+        let crosses_seam = u.iter().any(|&u| u < 0.25) && u.iter().any(|&u| u > 0.75);
+        let mut corners = triangle;
+        for corner in &mut corners {
+            if crosses_seam && *corner > 1 && mesh.uvs[*corner as usize].x < 0.5 {
+                *corner = *wrapped.entry(*corner).or_insert_with(|| {
+                    let copy = mesh.push_vertex_from(&source, *corner as usize);
+                    mesh.uvs[copy as usize].x += 1.0;
+                    copy
+                });
+            }
+        }
+        // The poles have no direction around the sphere, so each triangle there gets
+        // its own copy of the pole with `u` between its other two corners
+        if let Some(pole) = corners.iter().position(|&corner| corner <= 1) {
+            let others = [corners[(pole + 1) % 3], corners[(pole + 2) % 3]];
+            let u = (mesh.uvs[others[0] as usize].x + mesh.uvs[others[1] as usize].x) / 2.0;
+            let copy = mesh.push_vertex_from(&source, corners[pole] as usize);
+            mesh.uvs[copy as usize].x = u;
+            corners[pole] = copy;
+        }
+        mesh.indices.extend_from_slice(&corners);
+    }
+    mesh
+}
+
+/// A flat disc in the XZ plane facing up, made of `sectors` triangles around its center.
+pub fn disc(radius: f32, sectors: u32) -> MeshData {
+    assert!(sectors >= 3, "A disc needs at least 3 sectors");
+    let up = glm::vec3(0.0, 1.0, 0.0);
+    let mut mesh = MeshData {
+        name: "disc".to_string(),
+        positions: vec![glm::zero()],
+        normals: vec![up],
+        uvs: vec![glm::vec2(0.5, 0.5)],
+        ..MeshData::default()
+    };
+    for sector in 0..=sectors {
+        let (sin_angle, cos_angle) = turn(sector as f32 / sectors as f32);
+        mesh.positions.push(glm::vec3(cos_angle, 0.0, -sin_angle) * radius);
+        mesh.normals.push(up);
+        mesh.uvs.push(glm::vec2(0.5 + 0.5 * cos_angle, 0.5 + 0.5 * sin_angle));
+    }
+    for sector in 1..=sectors {
+        mesh.indices.extend_from_slice(&[0, sector, sector + 1]);
+    }
+    mesh
+}
+
+/// `cap` facing up at `height`, and a copy facing down at `-height`.
+fn caps(cap: &MeshData, height: f32) -> MeshData {
+    let mut mesh = cap.transformed(&glm::translation(&glm::vec3(0.0, height, 0.0)));
+    mesh.append(&cap.transformed(&glm::rotation(PI, &glm::vec3(1.0, 0.0, 0.0))
+        .append_translation(&glm::vec3(0.0, -height, 0.0))));
+    mesh
+}
+
+/// A closed cylinder around the Y axis. The side is split into `sectors` around and
+/// `stacks` along its height, and the texture wraps around it once.
+pub fn cylinder(radius: f32, height: f32, sectors: u32, stacks: u32) -> MeshData {
+    assert!(sectors >= 3 && stacks > 0, "A cylinder needs at least 3 sectors and 1 stack");
+    let mut mesh = grid("cylinder", sectors, stacks, |u, v| {
+        let (sin_angle, cos_angle) = turn(u);
+        let normal = glm::vec3(sin_angle, 0.0, cos_angle);
+        (normal * radius + glm::vec3(0.0, (v - 0.5) * height, 0.0), normal)
+    });
+    mesh.append(&caps(&disc(radius, sectors), height / 2.0));
+    mesh
+}
+
+/// A closed cone around the Y axis with its tip at the top. The side is split into
+/// `sectors` around and `stacks` along its height, and the texture wraps around it once.
+pub fn cone(radius: f32, height: f32, sectors: u32, stacks: u32) -> MeshData {
+    assert!(sectors >= 3 && stacks > 0, "A cone needs at least 3 sectors and 1 stack");
+    let mut mesh = grid("cone", sectors, stacks, |u, v| {
+        let (sin_angle, cos_angle) = turn(u);
+        let position = glm::vec3(sin_angle * radius * (1.0 - v), (v - 0.5) * height, cos_angle * radius * (1.0 - v));
+        let normal = glm::vec3(sin_angle * height, radius, cos_angle * height).normalize();
+        (position, normal)
+    });
+    let base = disc(radius, sectors).transformed(&glm::rotation(PI, &glm::vec3(1.0, 0.0, 0.0))
+        .append_translation(&glm::vec3(0.0, -height / 2.0, 0.0)));
+    mesh.append(&base);
+    mesh
+}
+
+/// A torus around the Y axis, with `radius` from its center to the middle of the tube.
+/// The ring is split into `sectors` and the tube around it into `sides`, with the
+/// texture wrapping once along each.
+pub fn torus(radius: f32, tube_radius: f32, sectors: u32, sides: u32) -> MeshData {
+    assert!(sectors >= 3 && sides >= 3, "A torus needs at least 3 sectors and 3 sides");
+    grid("torus", sectors, sides, |u, v| {
+        let (sin_angle, cos_angle) = turn(u);
+        let (sin_tube, cos_tube) = turn(v);
+        let center = glm::vec3(sin_angle, 0.0, cos_angle) * radius;
+        let normal = glm::vec3(cos_tube * sin_angle, sin_tube, cos_tube * cos_angle);
+        (center + normal * tube_radius, normal)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::{bits, normals};
+
+    use super::*;
+
+    /// Every triangle is wound counter-clockwise around the normals of its corners,
+    /// and every normal is a unit vector.
+    fn assert_consistent(mesh: &MeshData) {
+        assert_eq!(mesh.normals.len(), mesh.vertex_count());
+        assert_eq!(mesh.uvs.len(), mesh.vertex_count());
+        for normal in &mesh.normals {
+            assert!((normal.norm() - 1.0).abs() < 1e-5, "{} has a normal of length {}", mesh.name, normal.norm());
+        }
+        for triangle in mesh.triangles() {
+            let [a, b, c] = triangle.map(|index| mesh.positions[index as usize]);
+            let face = normals::face_normal(&a, &b, &c);
+            assert_ne!(face, glm::Vec3::zeros(), "{} has a degenerate triangle", mesh.name);
+            for index in triangle {
+                assert!(face.dot(&mesh.normals[index as usize]) > 0.0, "{} has a triangle wound the wrong way", mesh.name);
+            }
+        }
+    }
+
+    /// Every normal points away from `center(position)`.
+    fn assert_outward(mesh: &MeshData, center: impl Fn(&glm::Vec3) -> glm::Vec3) {
+        for (position, normal) in mesh.positions.iter().zip(&mesh.normals) {
+            assert!(normal.dot(&(position - center(position))) > 0.0, "{} has a normal pointing in", mesh.name);
+        }
+    }
+
+    fn distinct_positions(mesh: &MeshData) -> usize {
+        let mut positions: Vec<[u32; 3]> = mesh.positions.iter()
+            .map(bits)
+            .collect();
+        positions.sort_unstable();
+        positions.dedup();
+        positions.len()
+    }
+
+    #[test]
+    fn plane() {
+        let mesh = super::plane(2.0, 1.0, 4, 3);
+        assert_eq!(mesh.vertex_count(), 5 * 4);
+        assert_eq!(mesh.triangle_count(), 2 * 4 * 3);
+        assert_consistent(&mesh);
+        assert!(mesh.normals.iter().all(|normal| *normal == glm::vec3(0.0, 1.0, 0.0)));
+        assert_eq!(mesh.positions[0], glm::vec3(-1.0, 0.0, 0.5));
+        assert_eq!(mesh.uvs[mesh.vertex_count() - 1], glm::vec2(1.0, 1.0));
+    }
+
+    #[test]
+    fn cuboid() {
+        let mesh = super::cuboid(glm::vec3(1.0, 2.0, 3.0), 2);
+        assert_eq!(mesh.vertex_count(), 6 * 3 * 3);
+        assert_eq!(mesh.triangle_count(), 6 * 2 * 2 * 2);
+        assert_consistent(&mesh);
+        assert_outward(&mesh, |_| glm::zero());
+        for position in &mesh.positions {
+            assert!(position.x.abs() <= 0.5 && position.y.abs() <= 1.0 && position.z.abs() <= 1.5);
+        }
+    }
+
+    #[test]
+    fn uv_sphere() {
+        let mesh = super::uv_sphere(2.0, 8, 6);
+        assert_eq!(mesh.vertex_count(), 9 * 7);
+        // The seam and the poles are at exactly the same positions
+        assert_eq!(distinct_positions(&mesh), 8 * 5 + 2);
+        // The quads at the poles are single triangles
+        assert_eq!(mesh.triangle_count(), 2 * 8 * 6 - 2 * 8);
+        assert_consistent(&mesh);
+        assert_outward(&mesh, |_| glm::zero());
+        assert!(mesh.positions.iter().all(|position| (position.norm() - 2.0).abs() < 1e-5));
+    }
+
+    #[test]
+    fn icosphere() {
+        for subdivisions in 0..3 {
+            let mesh = super::icosphere(1.5, subdivisions);
+            let faces = 20 * 4usize.pow(subdivisions);
+            assert_eq!(mesh.triangle_count(), faces);
+            assert_eq!(distinct_positions(&mesh), faces / 2 + 2);
+            assert_consistent(&mesh);
+            assert_outward(&mesh, |_| glm::zero());
+            assert!(mesh.positions.iter().all(|position| (position.norm() - 1.5).abs() < 1e-5));
+            // No triangle stretches across the whole texture
+            for triangle in mesh.triangles() {
+                let u = triangle.map(|index| mesh.uvs[index as usize].x);
+                assert!((u[0] - u[1]).abs() < 0.5 && (u[1] - u[2]).abs() < 0.5 && (u[2] - u[0]).abs() < 0.5);
+            }
+        }
+    }
+
+    #[test]
+    fn disc() {
+        let mesh = super::disc(1.0, 12);
+        assert_eq!(mesh.vertex_count(), 1 + 13);
+        assert_eq!(mesh.triangle_count(), 12);
+        assert_consistent(&mesh);
+        assert!(mesh.normals.iter().all(|normal| *normal == glm::vec3(0.0, 1.0, 0.0)));
+    }
+
+    #[test]
+    fn cylinder() {
+        let mesh = super::cylinder(0.5, 2.0, 10, 3);
+        assert_eq!(mesh.vertex_count(), 11 * 4 + 2 * (1 + 11));
+        assert_eq!(mesh.triangle_count(), 2 * 10 * 3 + 2 * 10);
+        assert_consistent(&mesh);
+        // The sides point away from the axis and the caps away from the middle
+        assert_outward(&mesh, |position| {
+            if position.y.abs() < 1.0 { glm::vec3(0.0, position.y, 0.0) } else { glm::zero() }
+        });
+    }
+
+    #[test]
+    fn cone() {
+        let mesh = super::cone(1.0, 2.0, 10, 4);
+        assert_eq!(mesh.vertex_count(), 11 * 5 + 1 + 11);
+        // The quads at the tip are single triangles
+        assert_eq!(mesh.triangle_count(), 2 * 10 * 4 - 10 + 10);
+        assert_consistent(&mesh);
+        assert_outward(&mesh, |_| glm::vec3(0.0, -0.5, 0.0));
+    }
+
+    #[test]
+    fn torus() {
+        let mesh = super::torus(2.0, 0.5, 12, 8);
+        assert_eq!(mesh.vertex_count(), 13 * 9);
+        assert_eq!(distinct_positions(&mesh), 12 * 8);
+        assert_eq!(mesh.triangle_count(), 2 * 12 * 8);
+        assert_consistent(&mesh);
+        // Away from the middle of the tube
+        assert_outward(&mesh, |position| glm::vec3(position.x, 0.0, position.z).normalize() * 2.0);
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use super::{bits, MeshData};
+
+/// The triangles of a mesh as mikktspace sees them, and the tangent it gives every corner.
+struct Corners<'a> {
+    mesh: &'a MeshData,
+    tangents: Vec<glm::Vec4>,
+}
+
+impl Corners<'_> {
+    fn vertex(&self, face: usize, vert: usize) -> usize {
+        self.mesh.indices[face * 3 + vert] as usize
+    }
+}
+
+impl mikktspace::Geometry for Corners<'_> {
+    fn num_faces(&self) -> usize {
+        self.mesh.triangle_count()
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.mesh.positions[self.vertex(face, vert)].into()
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.mesh.normals[self.vertex(face, vert)].into()
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.mesh.uvs[self.vertex(face, vert)].into()
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        self.tangents[face * 3 + vert] = glm::make_vec4(&tangent);
+    }
+}
+
+/// A copy of `mesh` with tangents computed the way MikkTSpace does, replacing any it
+/// had, so normal maps baked by tools that use it are shaded as they were baked.
+/// `None` if the mesh has no normals or texture coordinates.
+///
+/// Tangents are computed per corner, so vertices are split where corners with
+/// different tangents meet, such as where the texture is mirrored.
+pub fn generate(mesh: &MeshData) -> Option<MeshData> {
+    if mesh.normals.is_empty() || mesh.uvs.is_empty() {
+        return None;
+    }
+    let mut corners = Corners { mesh, tangents: vec![glm::Vec4::zeros(); mesh.indices.len()] };
+    if mesh.triangle_count() > 0 && !mikktspace::generate_tangents(&mut corners) {
+        println!("WARNING: could not generate tangents for mesh \"{}\"", mesh.name);
+    }
+    Some(with_corner_tangents(mesh, &corners.tangents))
+}
+
+/// A copy of `mesh` with one tangent per entry of `indices`. Vertices are split where
+/// corners with different tangents meet, and stay shared where they have the same one.
+fn with_corner_tangents(mesh: &MeshData, tangents: &[glm::Vec4]) -> MeshData {
+    let mut result = mesh.empty_like();
+    let mut split: HashMap<(u32, [u32; 4]), u32> = HashMap::new();
+    for (index, tangent) in mesh.indices.iter().zip(tangents) {
+        let vertex = *split.entry((*index, bits(tangent))).or_insert_with(|| {
+            let vertex = result.push_vertex_from(mesh, *index as usize);
+            result.tangents.truncate(vertex as usize);
+            result.tangents.push(*tangent);
+            vertex
+        });
+        result.indices.push(vertex);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::primitives;
+
+    use super::*;
+
+    #[test]
+    fn plane_tangents_follow_u() {
+        let mesh = generate(&primitives::plane(1.0, 1.0, 2, 2)).unwrap();
+        assert_eq!(mesh.vertex_count(), 9);
+        for tangent in &mesh.tangents {
+            assert!(glm::distance(tangent, &glm::vec4(1.0, 0.0, 0.0, 1.0)) < 1e-5);
+        }
+    }
+
+    #[test]
+    fn mirrored_texture_flips_handedness() {
+        let mut plane = primitives::plane(1.0, 1.0, 1, 1);
+        plane.uvs.iter_mut().for_each(|uv| uv.x = 1.0 - uv.x);
+        let mesh = generate(&plane).unwrap();
+        for tangent in &mesh.tangents {
+            assert!(glm::distance(tangent, &glm::vec4(-1.0, 0.0, 0.0, -1.0)) < 1e-5);
+        }
+    }
+
+    #[test]
+    fn sphere_tangents_are_perpendicular_to_normals() {
+        let sphere = primitives::uv_sphere(1.0, 16, 8);
+        let mesh = generate(&sphere).unwrap();
+        assert_eq!(mesh.triangle_count(), sphere.triangle_count());
+        assert_eq!(mesh.tangents.len(), mesh.vertex_count());
+        for (tangent, normal) in mesh.tangents.iter().zip(&mesh.normals) {
+            assert!((tangent.xyz().norm() - 1.0).abs() < 1e-4);
+            assert!(tangent.xyz().dot(normal).abs() < 1e-4);
+            assert!(tangent.w.abs() == 1.0);
+        }
+    }
+
+    #[test]
+    fn signed_zeros_stay_welded() {
+        let plane = primitives::plane(1.0, 1.0, 1, 1);
+        let tangents: Vec<glm::Vec4> = (0..plane.indices.len())
+            .map(|corner| if corner % 2 == 0 { glm::vec4(1.0, 0.0, -0.0, 1.0) } else { glm::vec4(1.0, -0.0, 0.0, 1.0) })
+            .collect();
+        let mesh = with_corner_tangents(&plane, &tangents);
+        assert_eq!(mesh.vertex_count(), plane.vertex_count());
+        let corners = |mesh: &MeshData| mesh.indices.iter().map(|&index| mesh.positions[index as usize]).collect::<Vec<_>>();
+        assert_eq!(corners(&mesh), corners(&plane));
+    }
+
+    #[test]
+    fn needs_normals_and_uvs() {
+        let plane = primitives::plane(1.0, 1.0, 1, 1);
+        assert!(generate(&MeshData { uvs: vec![], ..plane.clone() }).is_none());
+        assert!(generate(&MeshData { normals: vec![], ..plane }).is_none());
+    }
+}
@@ -0,0 +1,247 @@
+// Owning wrappers around OpenGL object names.
+//
+// Each wrapper deletes its object when dropped. GL objects belong to the context
+// they were created in, and that context is only current on the render thread, so
+// none of these types are `Send` or `Sync`: they can't be moved to, or dropped on,
+// a thread where the delete call would silently do nothing.
+
+use std::marker::PhantomData;
+
+use gl::types::{GLenum, GLuint};
+
+// Raw pointers are neither Send nor Sync, which is exactly what we want here
+type NotSend = PhantomData<*const ()>;
+
+/// A buffer object, e.g. a VBO or IBO.
+#[derive(Debug)]
+pub struct Buffer {
+    id: GLuint,
+    _not_send: NotSend,
+}
+
+impl Buffer {
+    pub unsafe fn new() -> Buffer {
+        // Created rather than generated, so the direct state access functions can
+        // use it before it has ever been bound
+        let mut id = 0;
+        gl::CreateBuffers(1, &mut id);
+        Buffer { id, _not_send: PhantomData }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub unsafe fn bind(&self, target: GLenum) {
+        gl::BindBuffer(target, self.id);
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteBuffers(1, &self.id) }
+    }
+}
+
+/// A vertex array object. It also owns the buffers it reads from, so they live
+/// exactly as long as the VAO that references them.
+#[derive(Debug)]
+pub struct VertexArray {
+    id: GLuint,
+    buffers: Vec<Buffer>,
+    _not_send: NotSend,
+}
+
+impl VertexArray {
+    pub unsafe fn new() -> VertexArray {
+        let mut id = 0;
+        gl::CreateVertexArrays(1, &mut id);
+        VertexArray { id, buffers: vec![], _not_send: PhantomData }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub unsafe fn bind(&self) {
+        gl::BindVertexArray(self.id);
+    }
+
+    /// Keep `buffer` alive until this VAO is dropped.
+    pub fn own_buffer(&mut self, buffer: Buffer) {
+        self.buffers.push(buffer);
+    }
+
+    pub fn buffers(&self) -> &[Buffer] {
+        &self.buffers
+    }
+}
+
+impl Drop for VertexArray {
+    fn drop(&mut self) {
+        // The buffers are dropped after this, once the VAO no longer references them
+        unsafe { gl::DeleteVertexArrays(1, &self.id) }
+    }
+}
+
+/// A texture object.
+#[derive(Debug)]
+pub struct Texture {
+    id: GLuint,
+    _not_send: NotSend,
+}
+
+impl Texture {
+    pub unsafe fn new(target: GLenum) -> Texture {
+        let mut id = 0;
+        gl::CreateTextures(target, 1, &mut id);
+        Texture { id, _not_send: PhantomData }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    /// Bind to texture unit `unit`, i.e. the `binding` of a sampler in the shader.
+    pub unsafe fn bind(&self, unit: u32) {
+        gl::BindTextureUnit(unit, self.id);
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteTextures(1, &self.id) }
+    }
+}
+
+/// A program object.
+#[derive(Debug)]
+pub struct Program {
+    id: GLuint,
+    _not_send: NotSend,
+}
+
+impl Program {
+    pub unsafe fn new() -> Program {
+        Program { id: gl::CreateProgram(), _not_send: PhantomData }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+}
+
+impl Drop for Program {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteProgram(self.id) }
+    }
+}
+
+/// A program pipeline object, which combines stages from several separable programs.
+#[derive(Debug)]
+pub struct ProgramPipeline {
+    id: GLuint,
+    _not_send: NotSend,
+}
+
+impl ProgramPipeline {
+    pub unsafe fn new() -> ProgramPipeline {
+        let mut id = 0;
+        gl::CreateProgramPipelines(1, &mut id);
+        ProgramPipeline { id, _not_send: PhantomData }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+}
+
+impl Drop for ProgramPipeline {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteProgramPipelines(1, &self.id) }
+    }
+}
+
+/// A transform feedback object, holding the buffers vertex outputs are captured into.
+#[derive(Debug)]
+pub struct TransformFeedback {
+    id: GLuint,
+    _not_send: NotSend,
+}
+
+impl TransformFeedback {
+    pub unsafe fn new() -> TransformFeedback {
+        let mut id = 0;
+        gl::CreateTransformFeedbacks(1, &mut id);
+        TransformFeedback { id, _not_send: PhantomData }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    pub unsafe fn bind(&self) {
+        gl::BindTransformFeedback(gl::TRANSFORM_FEEDBACK, self.id);
+    }
+}
+
+impl Drop for TransformFeedback {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteTransformFeedbacks(1, &self.id) }
+    }
+}
+
+/// A query object, e.g. for counting the primitives written by transform feedback.
+#[derive(Debug)]
+pub struct Query {
+    id: GLuint,
+    _not_send: NotSend,
+}
+
+impl Query {
+    pub unsafe fn new(target: GLenum) -> Query {
+        let mut id = 0;
+        gl::CreateQueries(target, 1, &mut id);
+        Query { id, _not_send: PhantomData }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    /// Wait for the result. Stalls until the GPU has finished the queried commands.
+    pub unsafe fn result(&self) -> u64 {
+        let mut result = 0;
+        gl::GetQueryObjectui64v(self.id, gl::QUERY_RESULT, &mut result);
+        result
+    }
+}
+
+impl Drop for Query {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteQueries(1, &self.id) }
+    }
+}
+
+/// A shader object, i.e. one compiled stage before it is linked into a `Program`.
+#[derive(Debug)]
+pub struct ShaderObject {
+    id: GLuint,
+    _not_send: NotSend,
+}
+
+impl ShaderObject {
+    pub unsafe fn new(shader_type: GLenum) -> ShaderObject {
+        ShaderObject { id: gl::CreateShader(shader_type), _not_send: PhantomData }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+}
+
+impl Drop for ShaderObject {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteShader(self.id) }
+    }
+}
@@ -0,0 +1,579 @@
+// Memory layout of GLSL interface blocks, for filling uniform and storage buffers
+// from Rust values.
+//
+// `#[repr(C)]` does not match GLSL block layouts: a `vec3` is aligned to 16 bytes,
+// std140 pads array elements to 16 bytes, and so on. Instead, `BlockLayout` types
+// write and read themselves at the offsets the layout rules prescribe, and list
+// those offsets so they can be checked against what the driver reports.
+
+use std::fmt;
+
+use gl::types::GLenum;
+
+use crate::shader::{type_name, Block};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutRule {
+    /// The layout of `layout(std140) uniform` blocks.
+    Std140,
+    /// The layout of `layout(std430) buffer` blocks: like std140, without rounding
+    /// arrays and structs up to 16 bytes.
+    Std430,
+}
+
+/// Where one non-struct member of a block ends up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemberLayout {
+    /// The name as reported by reflection, e.g. `lights[1].color` or `weights[0]`.
+    pub name: String,
+    pub offset: usize,
+    pub gl_type: GLenum,
+    /// Stride between elements for arrays of non-structs, otherwise 0.
+    pub array_stride: usize,
+    /// Stride between columns for matrices, otherwise 0.
+    pub matrix_stride: usize,
+}
+
+/// A type that can be stored in a GLSL interface block.
+///
+/// Implemented for `f32`, `i32`, `u32`, `bool`, glm vectors and float matrices, and
+/// arrays of those. Use `block_layout!` to implement it for your own structs.
+pub trait BlockLayout: Sized {
+    /// Base alignment in bytes.
+    fn align(rule: LayoutRule) -> usize;
+    /// Size in bytes, including any padding at the end.
+    fn size(rule: LayoutRule) -> usize;
+    /// Write `self` to the start of `out`, which is at least `size(rule)` bytes long.
+    fn write(&self, rule: LayoutRule, out: &mut [u8]);
+    /// Read a value from the start of `bytes`, which is at least `size(rule)` bytes long.
+    fn read(rule: LayoutRule, bytes: &[u8]) -> Self;
+    /// Append the layout of every non-struct member, naming them like reflection does.
+    fn members(rule: LayoutRule, name: &str, offset: usize, out: &mut Vec<MemberLayout>);
+    /// Whether this type is a single member for reflection, as opposed to a struct.
+    /// Arrays of structs are reported element by element, arrays of others as a whole.
+    fn is_struct() -> bool { false }
+}
+
+pub fn round_up(value: usize, align: usize) -> usize {
+    value.div_ceil(align) * align
+}
+
+/// Stride between consecutive elements of an array, or columns of a matrix.
+pub fn array_stride(rule: LayoutRule, element_align: usize, element_size: usize) -> usize {
+    let align = array_align(rule, element_align);
+    round_up(element_size, align)
+}
+
+fn array_align(rule: LayoutRule, element_align: usize) -> usize {
+    match rule {
+        LayoutRule::Std140 => round_up(element_align, 16),
+        LayoutRule::Std430 => element_align,
+    }
+}
+
+/// Alignment of a struct whose most-aligned member has `max_member_align`.
+pub fn struct_align(rule: LayoutRule, max_member_align: usize) -> usize {
+    array_align(rule, max_member_align)
+}
+
+macro_rules! scalar_layout {
+    ($type:ty, $gl_type:expr) => {
+        impl BlockLayout for $type {
+            fn align(_rule: LayoutRule) -> usize { 4 }
+            fn size(_rule: LayoutRule) -> usize { 4 }
+            fn write(&self, _rule: LayoutRule, out: &mut [u8]) {
+                out[..4].copy_from_slice(&self.to_ne_bytes());
+            }
+            fn read(_rule: LayoutRule, bytes: &[u8]) -> Self {
+                <$type>::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+            }
+            fn members(_rule: LayoutRule, name: &str, offset: usize, out: &mut Vec<MemberLayout>) {
+                out.push(MemberLayout { name: name.to_string(), offset, gl_type: $gl_type, array_stride: 0, matrix_stride: 0 });
+            }
+        }
+    };
+}
+
+scalar_layout!(f32, gl::FLOAT);
+scalar_layout!(i32, gl::INT);
+scalar_layout!(u32, gl::UNSIGNED_INT);
+
+// GLSL bools are 4 bytes wide in blocks
+impl BlockLayout for bool {
+    fn align(rule: LayoutRule) -> usize { u32::align(rule) }
+    fn size(rule: LayoutRule) -> usize { u32::size(rule) }
+    fn write(&self, rule: LayoutRule, out: &mut [u8]) {
+        (*self as u32).write(rule, out);
+    }
+    fn read(rule: LayoutRule, bytes: &[u8]) -> Self {
+        u32::read(rule, bytes) != 0
+    }
+    fn members(_rule: LayoutRule, name: &str, offset: usize, out: &mut Vec<MemberLayout>) {
+        out.push(MemberLayout { name: name.to_string(), offset, gl_type: gl::BOOL, array_stride: 0, matrix_stride: 0 });
+    }
+}
+
+macro_rules! vector_layout {
+    ($type:ty, $scalar:ty, $components:expr, $gl_type:expr) => {
+        impl BlockLayout for $type {
+            // vec3 is aligned like vec4
+            fn align(_rule: LayoutRule) -> usize { if $components == 2 { 8 } else { 16 } }
+            fn size(_rule: LayoutRule) -> usize { 4 * $components }
+            fn write(&self, rule: LayoutRule, out: &mut [u8]) {
+                for (index, component) in self.iter().enumerate() {
+                    component.write(rule, &mut out[4 * index..]);
+                }
+            }
+            fn read(rule: LayoutRule, bytes: &[u8]) -> Self {
+                let components: Vec<$scalar> = (0..$components)
+                    .map(|index| <$scalar>::read(rule, &bytes[4 * index..]))
+                    .collect();
+                <$type>::from_column_slice(&components)
+            }
+            fn members(_rule: LayoutRule, name: &str, offset: usize, out: &mut Vec<MemberLayout>) {
+                out.push(MemberLayout { name: name.to_string(), offset, gl_type: $gl_type, array_stride: 0, matrix_stride: 0 });
+            }
+        }
+    };
+}
+
+vector_layout!(glm::Vec2, f32, 2, gl::FLOAT_VEC2);
+vector_layout!(glm::Vec3, f32, 3, gl::FLOAT_VEC3);
+vector_layout!(glm::Vec4, f32, 4, gl::FLOAT_VEC4);
+vector_layout!(glm::IVec2, i32, 2, gl::INT_VEC2);
+vector_layout!(glm::IVec3, i32, 3, gl::INT_VEC3);
+vector_layout!(glm::IVec4, i32, 4, gl::INT_VEC4);
+vector_layout!(glm::UVec2, u32, 2, gl::UNSIGNED_INT_VEC2);
+vector_layout!(glm::UVec3, u32, 3, gl::UNSIGNED_INT_VEC3);
+vector_layout!(glm::UVec4, u32, 4, gl::UNSIGNED_INT_VEC4);
+
+// Column-major matrices are laid out as an array of their column vectors
+macro_rules! matrix_layout {
+    ($type:ty, $column:ty, $columns:expr, $rows:expr, $gl_type:expr) => {
+        impl BlockLayout for $type {
+            fn align(rule: LayoutRule) -> usize {
+                array_align(rule, <$column>::align(rule))
+            }
+            fn size(rule: LayoutRule) -> usize {
+                $columns * array_stride(rule, <$column>::align(rule), <$column>::size(rule))
+            }
+            fn write(&self, rule: LayoutRule, out: &mut [u8]) {
+                let stride = array_stride(rule, <$column>::align(rule), <$column>::size(rule));
+                for column in 0..$columns {
+                    for row in 0..$rows {
+                        self[(row, column)].write(rule, &mut out[column * stride + 4 * row..]);
+                    }
+                }
+            }
+            fn read(rule: LayoutRule, bytes: &[u8]) -> Self {
+                let stride = array_stride(rule, <$column>::align(rule), <$column>::size(rule));
+                let components: Vec<f32> = (0..$columns)
+                    .flat_map(|column| (0..$rows).map(move |row| column * stride + 4 * row))
+                    .map(|offset| f32::read(rule, &bytes[offset..]))
+                    .collect();
+                <$type>::from_column_slice(&components)
+            }
+            fn members(rule: LayoutRule, name: &str, offset: usize, out: &mut Vec<MemberLayout>) {
+                let matrix_stride = array_stride(rule, <$column>::align(rule), <$column>::size(rule));
+                out.push(MemberLayout { name: name.to_string(), offset, gl_type: $gl_type, array_stride: 0, matrix_stride });
+            }
+        }
+    };
+}
+
+matrix_layout!(glm::Mat2, glm::Vec2, 2, 2, gl::FLOAT_MAT2);
+matrix_layout!(glm::Mat3, glm::Vec3, 3, 3, gl::FLOAT_MAT3);
+matrix_layout!(glm::Mat4, glm::Vec4, 4, 4, gl::FLOAT_MAT4);
+
+impl<T: BlockLayout, const N: usize> BlockLayout for [T; N] {
+    fn align(rule: LayoutRule) -> usize {
+        array_align(rule, T::align(rule))
+    }
+    fn size(rule: LayoutRule) -> usize {
+        N * array_stride(rule, T::align(rule), T::size(rule))
+    }
+    fn write(&self, rule: LayoutRule, out: &mut [u8]) {
+        let stride = array_stride(rule, T::align(rule), T::size(rule));
+        for (index, element) in self.iter().enumerate() {
+            element.write(rule, &mut out[index * stride..]);
+        }
+    }
+    fn read(rule: LayoutRule, bytes: &[u8]) -> Self {
+        let stride = array_stride(rule, T::align(rule), T::size(rule));
+        let mut index = 0;
+        [(); N].map(|_| {
+            let element = T::read(rule, &bytes[index * stride..]);
+            index += 1;
+            element
+        })
+    }
+    fn members(rule: LayoutRule, name: &str, offset: usize, out: &mut Vec<MemberLayout>) {
+        let stride = array_stride(rule, T::align(rule), T::size(rule));
+        if T::is_struct() {
+            for index in 0..N {
+                T::members(rule, &format!("{}[{}]", name, index), offset + index * stride, out);
+            }
+        } else {
+            let first = out.len();
+            T::members(rule, &format!("{}[0]", name), offset, out);
+            out[first].array_stride = stride;
+        }
+    }
+}
+
+/// Define a struct and implement `BlockLayout` for it, laying the fields out in order.
+///
+/// ```ignore
+/// block_layout! {
+///     pub struct Camera {
+///         pub view_projection: glm::Mat4,
+///         pub position: glm::Vec3,
+///         pub exposure: f32,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! block_layout {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($field_vis:vis $field:ident : $type:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($field_vis $field: $type),*
+        }
+
+        impl $crate::layout::BlockLayout for $name {
+            fn align(rule: $crate::layout::LayoutRule) -> usize {
+                let max_align = 0 $(.max(<$type as $crate::layout::BlockLayout>::align(rule)))*;
+                $crate::layout::struct_align(rule, max_align)
+            }
+            fn size(rule: $crate::layout::LayoutRule) -> usize {
+                let mut offset = 0;
+                $(
+                    offset = $crate::layout::round_up(offset, <$type as $crate::layout::BlockLayout>::align(rule));
+                    offset += <$type as $crate::layout::BlockLayout>::size(rule);
+                )*
+                $crate::layout::round_up(offset, <Self as $crate::layout::BlockLayout>::align(rule))
+            }
+            fn write(&self, rule: $crate::layout::LayoutRule, out: &mut [u8]) {
+                let mut offset = 0;
+                $(
+                    offset = $crate::layout::round_up(offset, <$type as $crate::layout::BlockLayout>::align(rule));
+                    $crate::layout::BlockLayout::write(&self.$field, rule, &mut out[offset..]);
+                    offset += <$type as $crate::layout::BlockLayout>::size(rule);
+                )*
+                let _ = offset;
+            }
+            fn read(rule: $crate::layout::LayoutRule, bytes: &[u8]) -> Self {
+                let mut offset = 0;
+                $(
+                    offset = $crate::layout::round_up(offset, <$type as $crate::layout::BlockLayout>::align(rule));
+                    let $field = <$type as $crate::layout::BlockLayout>::read(rule, &bytes[offset..]);
+                    offset += <$type as $crate::layout::BlockLayout>::size(rule);
+                )*
+                let _ = offset;
+                $name { $($field),* }
+            }
+            fn members(rule: $crate::layout::LayoutRule, name: &str, base: usize, out: &mut Vec<$crate::layout::MemberLayout>) {
+                let mut offset = 0;
+                $(
+                    offset = $crate::layout::round_up(offset, <$type as $crate::layout::BlockLayout>::align(rule));
+                    let field_name = if name.is_empty() {
+                        stringify!($field).to_string()
+                    } else {
+                        format!("{}.{}", name, stringify!($field))
+                    };
+                    <$type as $crate::layout::BlockLayout>::members(rule, &field_name, base + offset, out);
+                    offset += <$type as $crate::layout::BlockLayout>::size(rule);
+                )*
+                let _ = offset;
+            }
+            fn is_struct() -> bool { true }
+        }
+    };
+}
+
+/// Stride between consecutive `T`s in an array.
+pub fn element_stride<T: BlockLayout>(rule: LayoutRule) -> usize {
+    array_stride(rule, T::align(rule), T::size(rule))
+}
+
+/// Serialize `value` into a freshly allocated buffer of exactly `T::size(rule)` bytes.
+pub fn to_bytes<T: BlockLayout>(rule: LayoutRule, value: &T) -> Vec<u8> {
+    let mut bytes = vec![0u8; T::size(rule)];
+    value.write(rule, &mut bytes);
+    bytes
+}
+
+/// A difference between a Rust `BlockLayout` type and the block a program reports.
+#[derive(Debug, Clone)]
+pub enum LayoutMismatch {
+    /// The program has no active block by this name.
+    MissingBlock,
+    /// The Rust type is smaller than the block requires.
+    Size { rust: usize, glsl: usize },
+    /// The block has a member the Rust type does not.
+    MissingMember { member: String },
+    Offset { member: String, rust: usize, glsl: usize },
+    Type { member: String, rust: GLenum, glsl: GLenum },
+    ArrayStride { member: String, rust: usize, glsl: usize },
+    MatrixStride { member: String, rust: usize, glsl: usize },
+}
+
+impl fmt::Display for LayoutMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutMismatch::MissingBlock => write!(f, "no active block with this name"),
+            LayoutMismatch::Size { rust, glsl } => {
+                write!(f, "Rust type is {} bytes but the block needs {}", rust, glsl)
+            },
+            LayoutMismatch::MissingMember { member } => {
+                write!(f, "\"{}\" is in the block but not in the Rust type", member)
+            },
+            LayoutMismatch::Offset { member, rust, glsl } => {
+                write!(f, "\"{}\" is at offset {} in Rust but {} in GLSL", member, rust, glsl)
+            },
+            LayoutMismatch::Type { member, rust, glsl } => {
+                write!(f, "\"{}\" is a {} in Rust but a {} in GLSL", member, type_name(*rust), type_name(*glsl))
+            },
+            LayoutMismatch::ArrayStride { member, rust, glsl } => {
+                write!(f, "\"{}\" has array stride {} in Rust but {} in GLSL", member, rust, glsl)
+            },
+            LayoutMismatch::MatrixStride { member, rust, glsl } => {
+                write!(f, "\"{}\" has matrix stride {} in Rust but {} in GLSL", member, rust, glsl)
+            },
+        }
+    }
+}
+
+/// Mismatches between a Rust type and a named block, as returned when binding a buffer.
+#[derive(Debug, Clone)]
+pub struct LayoutError {
+    pub block: String,
+    pub mismatches: Vec<LayoutMismatch>,
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Layout of block \"{}\" does not match the Rust type:", self.block)?;
+        for mismatch in &self.mismatches {
+            write!(f, "\n  {}", mismatch)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for LayoutError {}
+
+/// Compare the members of `block` against `expected`. `rename` turns a reflected
+/// member name into the name used by `expected`, or `None` to skip the member.
+pub fn compare_members<'a>(
+    expected: &[MemberLayout],
+    block: &'a Block,
+    rename: impl Fn(&'a str) -> Option<&'a str>,
+) -> Vec<LayoutMismatch> {
+    let mut mismatches = vec![];
+    for member in &block.members {
+        let name = match rename(&member.name) {
+            Some(name) => name,
+            None => continue,
+        };
+        let rust = match expected.iter().find(|layout| layout.name == name) {
+            Some(rust) => rust,
+            None => {
+                mismatches.push(LayoutMismatch::MissingMember { member: name.to_string() });
+                continue;
+            },
+        };
+        let member_name = name.to_string();
+        if rust.gl_type != member.gl_type {
+            mismatches.push(LayoutMismatch::Type { member: member_name, rust: rust.gl_type, glsl: member.gl_type });
+        } else if rust.offset != member.offset as usize {
+            mismatches.push(LayoutMismatch::Offset { member: member_name, rust: rust.offset, glsl: member.offset as usize });
+        } else if member.array_stride > 0 && rust.array_stride != member.array_stride as usize {
+            mismatches.push(LayoutMismatch::ArrayStride { member: member_name, rust: rust.array_stride, glsl: member.array_stride as usize });
+        } else if member.matrix_stride > 0 && rust.matrix_stride != member.matrix_stride as usize {
+            mismatches.push(LayoutMismatch::MatrixStride { member: member_name, rust: rust.matrix_stride, glsl: member.matrix_stride as usize });
+        }
+    }
+    mismatches
+}
+
+/// Check that `T` matches the layout of `block` under `rule`, member by member.
+pub fn check_block<T: BlockLayout>(rule: LayoutRule, block: &Block) -> Result<(), LayoutError> {
+    let mut expected = vec![];
+    T::members(rule, "", 0, &mut expected);
+
+    // Members of blocks with an instance name are reported as "Block.member"
+    let prefix = format!("{}.", block.name);
+    let mut mismatches = compare_members(&expected, block, |name| Some(name.strip_prefix(prefix.as_str()).unwrap_or(name)));
+    if T::size(rule) < block.data_size as usize {
+        mismatches.push(LayoutMismatch::Size { rust: T::size(rule), glsl: block.data_size as usize });
+    }
+
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(LayoutError { block: block.name.clone(), mismatches })
+    }
+}
+
+/// Check that `T` matches the elements of a block holding a single runtime-sized
+/// array, e.g. `buffer Particles { Particle particles[]; };`.
+pub fn check_runtime_array<T: BlockLayout>(rule: LayoutRule, block: &Block) -> Result<(), LayoutError> {
+    let stride = element_stride::<T>(rule);
+    let mut expected = vec![];
+    T::members(rule, "", 0, &mut expected);
+    if !T::is_struct() {
+        // A plain `float values[]` is reported as a single array member
+        expected[0].array_stride = stride;
+    }
+
+    // "Block.particles[0].position" or "particles[0].position" becomes "position",
+    // and "values[0]" becomes ""
+    let prefix = format!("{}.", block.name);
+    let mut mismatches = compare_members(&expected, block, |name| {
+        let name = name.strip_prefix(prefix.as_str()).unwrap_or(name);
+        let element = &name[name.find("[0]")? + 3..];
+        Some(element.strip_prefix('.').unwrap_or(element))
+    });
+    for member in block.members.iter().filter(|member| T::is_struct() && member.top_level_array_stride > 0) {
+        if member.top_level_array_stride as usize != stride {
+            mismatches.push(LayoutMismatch::ArrayStride {
+                member: member.name.clone(),
+                rust: stride,
+                glsl: member.top_level_array_stride as usize,
+            });
+            break;
+        }
+    }
+
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(LayoutError { block: block.name.clone(), mismatches })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    block_layout! {
+        #[derive(Debug, Clone, PartialEq)]
+        struct Light {
+            position: glm::Vec3,
+            intensity: f32,
+            color: glm::Vec3,
+        }
+    }
+
+    block_layout! {
+        #[derive(Debug, Clone, PartialEq)]
+        struct Scene {
+            normal_matrix: glm::Mat3,
+            weights: [f32; 3],
+            lights: [Light; 2],
+            ambient: glm::Vec2,
+            exposure: f32,
+        }
+    }
+
+    fn members<T: BlockLayout>(rule: LayoutRule) -> Vec<(String, usize, usize, usize)> {
+        let mut out = vec![];
+        T::members(rule, "", 0, &mut out);
+        out.into_iter()
+            .map(|member| (member.name, member.offset, member.array_stride, member.matrix_stride))
+            .collect()
+    }
+
+    fn expected(members: &[(&str, usize, usize, usize)]) -> Vec<(String, usize, usize, usize)> {
+        members.iter()
+            .map(|&(name, offset, array_stride, matrix_stride)| (name.to_string(), offset, array_stride, matrix_stride))
+            .collect()
+    }
+
+    fn scene() -> Scene {
+        let light = |x: f32| Light { position: glm::vec3(x, 2.0, 3.0), intensity: x * 10.0, color: glm::vec3(0.5, x, 0.25) };
+        Scene {
+            normal_matrix: glm::mat3(1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0),
+            weights: [0.25, 0.5, 0.75],
+            lights: [light(1.0), light(2.0)],
+            ambient: glm::vec2(0.1, 0.2),
+            exposure: 1.5,
+        }
+    }
+
+    #[test]
+    fn scalar_packs_after_vec3() {
+        for rule in [LayoutRule::Std140, LayoutRule::Std430] {
+            assert_eq!(members::<Light>(rule), expected(&[
+                ("position", 0, 0, 0),
+                ("intensity", 12, 0, 0),
+                ("color", 16, 0, 0),
+            ]));
+            assert_eq!(Light::align(rule), 16);
+            assert_eq!(Light::size(rule), 32);
+        }
+    }
+
+    #[test]
+    fn std140_rounds_columns_arrays_and_structs_to_16() {
+        assert_eq!(members::<Scene>(LayoutRule::Std140), expected(&[
+            ("normal_matrix", 0, 0, 16),
+            ("weights[0]", 48, 16, 0),
+            ("lights[0].position", 96, 0, 0),
+            ("lights[0].intensity", 108, 0, 0),
+            ("lights[0].color", 112, 0, 0),
+            ("lights[1].position", 128, 0, 0),
+            ("lights[1].intensity", 140, 0, 0),
+            ("lights[1].color", 144, 0, 0),
+            ("ambient", 160, 0, 0),
+            ("exposure", 168, 0, 0),
+        ]));
+        assert_eq!(Scene::size(LayoutRule::Std140), 176);
+        assert_eq!(element_stride::<f32>(LayoutRule::Std140), 16);
+        assert_eq!(element_stride::<Light>(LayoutRule::Std140), 32);
+    }
+
+    #[test]
+    fn std430_packs_scalar_arrays() {
+        assert_eq!(members::<Scene>(LayoutRule::Std430), expected(&[
+            ("normal_matrix", 0, 0, 16),
+            ("weights[0]", 48, 4, 0),
+            ("lights[0].position", 64, 0, 0),
+            ("lights[0].intensity", 76, 0, 0),
+            ("lights[0].color", 80, 0, 0),
+            ("lights[1].position", 96, 0, 0),
+            ("lights[1].intensity", 108, 0, 0),
+            ("lights[1].color", 112, 0, 0),
+            ("ambient", 128, 0, 0),
+            ("exposure", 136, 0, 0),
+        ]));
+        assert_eq!(Scene::size(LayoutRule::Std430), 144);
+        assert_eq!(element_stride::<f32>(LayoutRule::Std430), 4);
+        assert_eq!(<[glm::Vec2; 3]>::size(LayoutRule::Std430), 24);
+        assert_eq!(<[glm::Vec2; 3]>::size(LayoutRule::Std140), 48);
+    }
+
+    #[test]
+    fn values_are_written_at_member_offsets() {
+        for rule in [LayoutRule::Std140, LayoutRule::Std430] {
+            let scene = scene();
+            let bytes = to_bytes(rule, &scene);
+            assert_eq!(bytes.len(), Scene::size(rule));
+            assert_eq!(Scene::read(rule, &bytes), scene);
+
+            let mut layout = vec![];
+            Scene::members(rule, "", 0, &mut layout);
+            let offset = |name: &str| layout.iter().find(|member| member.name == name).unwrap().offset;
+            assert_eq!(f32::read(rule, &bytes[offset("exposure")..]), 1.5);
+            assert_eq!(f32::read(rule, &bytes[offset("lights[1].intensity")..]), 20.0);
+            // The third column of the matrix starts two matrix strides in
+            assert_eq!(f32::read(rule, &bytes[offset("normal_matrix") + 32..]), 3.0);
+        }
+    }
+}
@@ -0,0 +1,20 @@
+// The building blocks the exercises in `main.rs` use: shaders, buffers, meshes and
+// model loading.
+//
+// Every `unsafe fn` in this crate is unsafe for the same reason: it makes OpenGL calls,
+// which need a current context on the calling thread with the function pointers loaded.
+// That is documented once here instead of in a `# Safety` section on each of them.
+#![allow(clippy::missing_safety_doc)]
+
+extern crate nalgebra_glm as glm;
+
+#[macro_use]
+pub mod layout;
+pub mod geometry;
+pub mod gl_object;
+pub mod mesh;
+pub mod model;
+pub mod shader;
+pub mod storage_buffer;
+pub mod uniform_buffer;
+pub mod util;
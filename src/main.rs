@@ -9,19 +9,16 @@
 */
 extern crate nalgebra_glm as glm;
 
-use rand::prelude::*;
 use std::{mem, ptr, os::raw::c_void};
 use std::thread;
 use std::sync::{Mutex, Arc, RwLock};
-use gl::types::{GLfloat, GLsizei};
-use glm::vec4;
-
-mod shader;
-mod util;
+use gl::types::GLfloat;
 
 use glutin::event::{Event, WindowEvent, DeviceEvent, KeyboardInput, ElementState::{Pressed, Released}, VirtualKeyCode::{self, *}};
 use glutin::event_loop::ControlFlow;
-use crate::shader::Shader;
+use gloom_rs::mesh::{Mesh, VertexAttribute, VertexLayout};
+use gloom_rs::shader::{self, ReloadableShader};
+use gloom_rs::util;
 
 // initial window size
 const INITIAL_SCREEN_W: u32 = 800;
@@ -31,28 +28,33 @@ const INITIAL_SCREEN_H: u32 = 600;
 
 // Get the size of an arbitrary array of numbers measured in bytes
 // Example usage:  pointer_to_array(my_array)
+#[allow(dead_code)]
 fn byte_size_of_array<T>(val: &[T]) -> isize {
-    std::mem::size_of_val(&val[..]) as isize
+    std::mem::size_of_val(val) as isize
 }
 
 // Get the OpenGL-compatible pointer to an arbitrary array of numbers
 // Example usage:  pointer_to_array(my_array)
+#[allow(dead_code)]
 fn pointer_to_array<T>(val: &[T]) -> *const c_void {
     &val[0] as *const T as *const c_void
 }
 
+#[allow(dead_code)]
 fn raw_pointer_to_mutable_array<T>(val: &[T]) -> *mut GLfloat {
     &val[0] as *const T as *mut gl::types::GLfloat
 }
 
 // Get the size of the given type in bytes
 // Example usage:  size_of::<u64>()
+#[allow(dead_code)]
 fn size_of<T>() -> i32 {
     mem::size_of::<T>() as i32
 }
 
 // Get an offset in bytes for n units of type T, represented as a relative pointer
 // Example usage:  offset::<u64>(4)
+#[allow(dead_code)]
 fn offset<T>(n: u32) -> *const c_void {
     (n * mem::size_of::<T>() as u32) as *const T as *const c_void
 }
@@ -61,96 +63,6 @@ fn offset<T>(n: u32) -> *const c_void {
 // ptr::null()
 
 
-const VERTEX_VAO_INDEX: gl::types::GLuint = 0;
-
-const COLOR_VAO_INDEX: gl::types::GLuint = 4;
-
-const UNIFORM_INDEX: gl::types::GLint = 2;
-
-
-// == // Generate your VAO here
-unsafe fn create_vao(vertices: &Vec<f32>, indices: &Vec<u32>, colors: &Vec<f32>) -> u32 {
-    let infer_stride_from_rank_and_datatype: gl::types::GLsizei = 0;
-    let create_single_object: gl::types::GLsizei = 1;
-    const IGNORED_INITIAL_VALUE: u32 = 0;
-    // * Generate a VAO and bind it
-
-    let ignored_initial_value = 0;
-    let mut vertex_array_object_id: u32 = ignored_initial_value;
-    gl::GenVertexArrays(create_single_object, &mut vertex_array_object_id);
-    gl::BindVertexArray(vertex_array_object_id);
-
-    // * Generate a VBO and bind it
-    let mut vertex_buffer_id: u32 = ignored_initial_value;
-    gl::GenBuffers(create_single_object, &mut vertex_buffer_id);
-    gl::BindBuffer(gl::ARRAY_BUFFER, vertex_buffer_id);
-
-    // * Fill it with data
-    let size_of_vertex_array_in_bytes = byte_size_of_array(&vertices[..]);
-    gl::BufferData(gl::ARRAY_BUFFER,
-                   size_of_vertex_array_in_bytes,
-                   pointer_to_array(&vertices[..]),
-                   gl::STATIC_DRAW,
-    );
-
-    let rank_of_vertex_array = 3;
-    let stride = infer_stride_from_rank_and_datatype;
-    gl::VertexAttribPointer(
-        VERTEX_VAO_INDEX,
-        rank_of_vertex_array,
-        gl::FLOAT,
-        gl::FALSE,
-        stride,
-        ptr::null(),
-    );
-
-    gl::EnableVertexAttribArray(VERTEX_VAO_INDEX);
-
-    // * Generate a IBO and bind it
-
-    let mut color_buffer_object_id = ignored_initial_value;
-
-    gl::GenBuffers(create_single_object, &mut color_buffer_object_id);
-    gl::BindBuffer(gl::ARRAY_BUFFER, color_buffer_object_id);
-    // * Fill it with data
-
-    let size_of_color_vector_in_bytes = byte_size_of_array(&colors);
-    gl::BufferData(
-        gl::ARRAY_BUFFER,
-        size_of_color_vector_in_bytes,
-        pointer_to_array(&&colors[..]), gl::STATIC_DRAW,
-    );
-
-    let rank_of_color_array = 4;
-    let stride = infer_stride_from_rank_and_datatype;
-    gl::VertexAttribPointer(
-        COLOR_VAO_INDEX,
-        rank_of_color_array,
-        gl::FLOAT,
-        gl::FALSE,
-        stride,
-        ptr::null(),
-    );
-    gl::EnableVertexAttribArray(COLOR_VAO_INDEX);
-
-    // * Generate a IBO and bind it
-
-    let mut index_buffer_object_id = 0;
-    gl::GenBuffers(create_single_object, &mut index_buffer_object_id);
-    gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, index_buffer_object_id);
-    // * Fill it with data
-
-    let size_of_index_vector_in_bytes = byte_size_of_array(&indices);
-    gl::BufferData(
-        gl::ELEMENT_ARRAY_BUFFER,
-        size_of_index_vector_in_bytes,
-        pointer_to_array(&&indices[..]), gl::STATIC_DRAW,
-    );
-    // * Return the ID of the VAO
-    return vertex_array_object_id;
-}
-
-
 fn main() {
     // Set up the necessary objects to deal with windows and event handling
     let el = glutin::event_loop::EventLoop::new();
@@ -180,7 +92,9 @@ fn main() {
     // Make a reference of this tuple to send to the render thread
     let window_size = Arc::clone(&arc_window_size);
 
-    // Spawn a separate thread for rendering, so event handling doesn't block rendering
+    // Spawn a separate thread for rendering, so event handling doesn't block rendering.
+    // `window_aspect_ratio` is updated on resize but only read once exercise 3 is done.
+    #[allow(unused_assignments)]
     let render_thread = thread::spawn(move || {
         // Acquire the OpenGL Context and load the function pointers.
         // This has to be done inside of the rendering thread, because
@@ -191,6 +105,8 @@ fn main() {
             c
         };
 
+        // Unused until the projection in exercise 3 is filled in
+        #[allow(unused_variables)]
         let mut window_aspect_ratio = INITIAL_SCREEN_W as f32 / INITIAL_SCREEN_H as f32;
 
 
@@ -214,7 +130,7 @@ fn main() {
 
         // == // Set up your VAO around here
 
-        let vertices = vec![
+        let vertices: Vec<f32> = vec![
             -1.0, -1.0, 0.5,
             0.0, -1.0, 0.5,
             -0.5, 0.0, 0.5,
@@ -225,12 +141,12 @@ fn main() {
             0.75, -0.5, -0.9,
             0.25, 0.25, -0.9,
         ];
-        let triangles = vec![0, 1, 2,
+        let triangles: Vec<u32> = vec![0, 1, 2,
                              3, 4, 5,
                              6, 7, 8,
         ];
         let alpha = 0.6;
-        let mut colors: Vec<f32> = vec![
+        let colors: Vec<f32> = vec![
             0.8, 0.0, 0.4, alpha,
             0.8, 0.0, 0.4, alpha,
             0.8, 0.0, 0.4, alpha,
@@ -241,15 +157,19 @@ fn main() {
             0.5, 0.5, 0.5, alpha,
             0.5, 0.5, 0.5, alpha,
         ];
-        let vao_1 = unsafe {
-            create_vao(&vertices, &triangles, &colors)
+        let layout = VertexLayout::separate()
+            .with(VertexAttribute::position())
+            .with(VertexAttribute::color());
+        let mesh_1 = unsafe {
+            Mesh::new(&layout, &[(&vertices).into(), (&colors).into()], Some(&triangles))
+                .unwrap_or_else(|error| panic!("{}", error))
         };
 
         // == // Set up your shaders here
 
         // Basic usage of shader helper:
         // The example code below creates a 'shader' object.
-        // It which contains the field `.program_id` and the method `.activate()`.
+        // It which contains the method `.program_id()` and the method `.activate()`.
         // The `.` in the path is relative to `Cargo.toml`.
         // This snippet is not enough to do the exercise, and will need to be modified (outside
         // of just using the correct path), but it only needs to be called once
@@ -257,26 +177,31 @@ fn main() {
 
         let path_to_fragment_shader = "./shaders/simple.frag";
         let path_to_vertex_shader = "./shaders/simple.vert";
-        let shader_program: Shader =
+        let mut shader_program: ReloadableShader =
             unsafe {
+                // Debug builds read the files on disk so edits are hot reloaded, release
+                // builds use the copies compiled into the executable
                 let program = shader::ShaderBuilder::new()
+                    .prefer_disk(cfg!(debug_assertions))
                     .attach_file(path_to_vertex_shader)
-                    .attach_file(path_to_fragment_shader)
-                    .link();
+                    .and_then(|builder| builder.attach_file(path_to_fragment_shader))
+                    .and_then(|builder| builder.link())
+                    .unwrap_or_else(|error| panic!("{}", error));
 
                 program.activate();
-                program
+                ReloadableShader::new(program)
             };
 
+        #[allow(unused_variables)]
         let uniform_matrix = unsafe {
             let initial_matrix: glm::Mat4 = glm::identity();
-            gl::UniformMatrix4fv(UNIFORM_INDEX, 1, false as gl::types::GLboolean, initial_matrix.as_ptr());
+            shader_program.shader().set_uniform("transformation_matrix", &initial_matrix);
             initial_matrix
         };
 
         // Used to demonstrate keyboard handling for exercise 2.
         let mut translation_vector_from_keypresses = [0.0, 0.0, 0.0];
-        let mut camera_rotation = vec![0.0,0.0];
+        let mut camera_rotation = [0.0,0.0];
 
         // The main rendering loop
         let first_frame_time = std::time::Instant::now();
@@ -284,6 +209,7 @@ fn main() {
         loop {
             // Compute time passed since the previous frame and since the start of the program
             let now = std::time::Instant::now();
+            #[allow(unused_variables)]
             let elapsed = now.duration_since(first_frame_time).as_secs_f32();
             let delta_time = now.duration_since(previous_frame_time).as_secs_f32();
             previous_frame_time = now;
@@ -293,7 +219,7 @@ fn main() {
                 if new_size.2 {
                     context.resize(glutin::dpi::PhysicalSize::new(new_size.0, new_size.1));
                     window_aspect_ratio = new_size.0 as f32 / new_size.1 as f32;
-                    (*new_size).2 = false;
+                    new_size.2 = false;
                     println!("Window was resized to {}x{}", new_size.0, new_size.1);
                     unsafe { gl::Viewport(0, 0, new_size.0 as i32, new_size.1 as i32); }
                 }
@@ -354,12 +280,26 @@ fn main() {
             // == // Please compute camera transforms here (exercise 2 & 3)
 
 
+            // Pick up any edits to the shader files since the last frame
+            unsafe {
+                match shader_program.reload_if_changed() {
+                    Ok(true) => {
+                        println!("Shaders reloaded");
+                        shader_program.shader().activate();
+                    }
+                    Ok(false) => {}
+                    Err(error) => println!("Keeping previous shader program: {}", error),
+                }
+            }
+
             unsafe {
                 // Clear the color and depth buffers
                 gl::ClearColor(0.035, 0.046, 0.078, 1.0); // night sky, full opacity
                 gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
 
                 // == // Issue the necessary gl:: commands to draw your scene here
+                // Reassigned by the transforms commented out below
+                #[allow(unused_mut)]
                 let mut transformation_matrix: glm::Mat4 = glm::identity();
                 // transformation_matrix = glm::translation(
                 //     &glm::vec3(0.0, 0.0, -2.0)
@@ -379,16 +319,9 @@ fn main() {
                 //     100.0,
                 // ) * transformation_matrix;
 
-                gl::UniformMatrix4fv(UNIFORM_INDEX, 1, false as gl::types::GLboolean, transformation_matrix.as_ptr());
+                shader_program.shader().set_uniform("transformation_matrix", &transformation_matrix);
 
-                gl::BindVertexArray(vao_1);
-                let size_of_indices_vector = triangles.len() as gl::types::GLsizei;
-                gl::DrawElements(
-                    gl::TRIANGLES,
-                    size_of_indices_vector,
-                    gl::UNSIGNED_INT,
-                    0 as *const _,
-                );
+                mesh_1.draw_with(shader_program.shader());
             }
 
 
@@ -407,7 +340,7 @@ fn main() {
     let render_thread_healthy = Arc::new(RwLock::new(true));
     let render_thread_watchdog = Arc::clone(&render_thread_healthy);
     thread::spawn(move || {
-        if !render_thread.join().is_ok() {
+        if render_thread.join().is_err() {
             if let Ok(mut health) = render_thread_watchdog.write() {
                 println!("Render thread panicked!");
                 *health = false;
@@ -421,7 +354,7 @@ fn main() {
 
         // Terminate program if render thread panics
         if let Ok(health) = render_thread_healthy.read() {
-            if *health == false {
+            if !*health {
                 *control_flow = ControlFlow::Exit;
             }
         }
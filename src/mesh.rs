@@ -0,0 +1,379 @@
+// Meshes on the GPU, described by a `VertexLayout` instead of hand-written
+// `glVertexAttribPointer` calls.
+
+use std::fmt;
+
+use gl::types::GLenum;
+
+use crate::gl_object::{Buffer, VertexArray};
+use crate::shader::Shader;
+
+/// The type of each component of an attribute, as stored in the buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentType {
+    Float,
+    Byte,
+    UnsignedByte,
+    Short,
+    UnsignedShort,
+    Int,
+    UnsignedInt,
+}
+
+impl ComponentType {
+    pub fn size(self) -> usize {
+        match self {
+            ComponentType::Byte | ComponentType::UnsignedByte   => { 1 },
+            ComponentType::Short | ComponentType::UnsignedShort => { 2 },
+            ComponentType::Float | ComponentType::Int | ComponentType::UnsignedInt => { 4 },
+        }
+    }
+
+    pub fn is_integer(self) -> bool {
+        self != ComponentType::Float
+    }
+}
+
+impl From<ComponentType> for GLenum {
+    fn from(component_type: ComponentType) -> Self {
+        match component_type {
+            ComponentType::Float         => { gl::FLOAT          },
+            ComponentType::Byte          => { gl::BYTE           },
+            ComponentType::UnsignedByte  => { gl::UNSIGNED_BYTE  },
+            ComponentType::Short         => { gl::SHORT          },
+            ComponentType::UnsignedShort => { gl::UNSIGNED_SHORT },
+            ComponentType::Int           => { gl::INT            },
+            ComponentType::UnsignedInt   => { gl::UNSIGNED_INT   },
+        }
+    }
+}
+
+/// Rust types that can be uploaded as attribute components.
+pub trait VertexComponent: Copy {
+    const TYPE: ComponentType;
+    fn to_bytes(values: &[Self]) -> Vec<u8>;
+}
+
+macro_rules! impl_vertex_component {
+    ($($rust_type:ty => $component_type:ident),* $(,)?) => {
+        $(
+            impl VertexComponent for $rust_type {
+                const TYPE: ComponentType = ComponentType::$component_type;
+                fn to_bytes(values: &[Self]) -> Vec<u8> {
+                    values.iter().flat_map(|value| value.to_ne_bytes()).collect()
+                }
+            }
+        )*
+    };
+}
+
+impl_vertex_component! {
+    f32 => Float,
+    i8  => Byte,
+    u8  => UnsignedByte,
+    i16 => Short,
+    u16 => UnsignedShort,
+    i32 => Int,
+    u32 => UnsignedInt,
+}
+
+/// One attribute of a vertex, read by the shader input at `location`.
+///
+/// Float components, and integer components with `normalized` set, are read as
+/// `float`/`vec*` in the shader, with normalized integers mapped to [0, 1] or [-1, 1].
+/// Integer components without `normalized` are read as `int`/`uint`/`ivec*`/`uvec*`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VertexAttribute {
+    pub name: String,
+    pub location: u32,
+    pub components: i32,
+    pub component_type: ComponentType,
+    pub normalized: bool,
+}
+
+impl VertexAttribute {
+    pub fn new(name: &str, location: u32, components: i32, component_type: ComponentType, normalized: bool) -> VertexAttribute {
+        assert!((1..=4).contains(&components), "vertex attributes have 1 to 4 components, \"{}\" has {}", name, components);
+        VertexAttribute { name: name.to_string(), location, components, component_type, normalized }
+    }
+
+    /// `vec3 position` at location 0.
+    pub fn position() -> VertexAttribute {
+        VertexAttribute::new("position", 0, 3, ComponentType::Float, false)
+    }
+
+    /// `vec3 normal` at location 1.
+    pub fn normal() -> VertexAttribute {
+        VertexAttribute::new("normal", 1, 3, ComponentType::Float, false)
+    }
+
+    /// `vec2 uv` at location 2.
+    pub fn uv() -> VertexAttribute {
+        VertexAttribute::new("uv", 2, 2, ComponentType::Float, false)
+    }
+
+    /// `vec4 tangent` at location 3, with the handedness of the bitangent in `w`.
+    pub fn tangent() -> VertexAttribute {
+        VertexAttribute::new("tangent", 3, 4, ComponentType::Float, false)
+    }
+
+    /// `vec4 color` at location 4.
+    pub fn color() -> VertexAttribute {
+        VertexAttribute::new("color", 4, 4, ComponentType::Float, false)
+    }
+
+    /// Bytes per vertex.
+    pub fn size(&self) -> usize {
+        self.components as usize * self.component_type.size()
+    }
+}
+
+/// How vertices are laid out in buffers: every attribute in one buffer, one vertex
+/// after the other, or each attribute in a buffer of its own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VertexLayout {
+    attributes: Vec<VertexAttribute>,
+    interleaved: bool,
+}
+
+// Attributes inside an interleaved vertex start on 4-byte boundaries
+const ATTRIBUTE_ALIGNMENT: usize = 4;
+
+impl VertexLayout {
+    pub fn interleaved() -> VertexLayout {
+        VertexLayout { attributes: vec![], interleaved: true }
+    }
+
+    pub fn separate() -> VertexLayout {
+        VertexLayout { attributes: vec![], interleaved: false }
+    }
+
+    pub fn with(mut self, attribute: VertexAttribute) -> VertexLayout {
+        self.attributes.push(attribute);
+        self
+    }
+
+    pub fn attributes(&self) -> &[VertexAttribute] {
+        &self.attributes
+    }
+
+    pub fn is_interleaved(&self) -> bool {
+        self.interleaved
+    }
+
+    /// Byte offset of each attribute within an interleaved vertex, and the vertex size.
+    pub fn offsets(&self) -> (Vec<usize>, usize) {
+        let mut offsets = vec![];
+        let mut stride = 0;
+        for attribute in &self.attributes {
+            offsets.push(stride);
+            stride += attribute.size().div_ceil(ATTRIBUTE_ALIGNMENT) * ATTRIBUTE_ALIGNMENT;
+        }
+        (offsets, stride)
+    }
+}
+
+/// The components of one attribute for every vertex, e.g. `[x0, y0, z0, x1, y1, z1, ...]`.
+#[derive(Debug, Clone)]
+pub struct AttributeData {
+    bytes: Vec<u8>,
+    len: usize,
+    component_type: ComponentType,
+}
+
+impl<T: VertexComponent> From<&[T]> for AttributeData {
+    fn from(values: &[T]) -> AttributeData {
+        AttributeData { bytes: T::to_bytes(values), len: values.len(), component_type: T::TYPE }
+    }
+}
+
+impl<T: VertexComponent> From<&Vec<T>> for AttributeData {
+    fn from(values: &Vec<T>) -> AttributeData {
+        AttributeData::from(values.as_slice())
+    }
+}
+
+/// Everything that can be wrong with the data a `Mesh` is created from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MeshError {
+    /// The number of data arrays doesn't match the number of attributes in the layout.
+    AttributeCount { layout: usize, data: usize },
+    /// The data for an attribute has a different component type than the layout says.
+    ComponentType { attribute: String, layout: ComponentType, data: ComponentType },
+    /// The data for an attribute isn't a whole number of vertices.
+    PartialVertex { attribute: String, components: usize },
+    /// Two attributes have data for a different number of vertices.
+    VertexCount { attribute: String, expected: usize, found: usize },
+    /// An index refers past the last vertex.
+    IndexOutOfRange { index: u32, vertex_count: usize },
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshError::AttributeCount { layout, data } => {
+                write!(f, "Vertex layout has {} attributes but data was given for {}", layout, data)
+            },
+            MeshError::ComponentType { attribute, layout, data } => {
+                write!(f, "Attribute \"{}\" is laid out as {:?} but its data is {:?}", attribute, layout, data)
+            },
+            MeshError::PartialVertex { attribute, components } => {
+                write!(f, "Attribute \"{}\" has {} components, which is not a whole number of vertices", attribute, components)
+            },
+            MeshError::VertexCount { attribute, expected, found } => {
+                write!(f, "Attribute \"{}\" has {} vertices, expected {}", attribute, found, expected)
+            },
+            MeshError::IndexOutOfRange { index, vertex_count } => {
+                write!(f, "Index {} is out of range for a mesh with {} vertices", index, vertex_count)
+            },
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+/// A vertex array with its buffers, ready to be drawn with one call.
+pub struct Mesh {
+    vertex_array: VertexArray,
+    layout: VertexLayout,
+    vertex_count: usize,
+    index_count: Option<usize>,
+    primitive: GLenum,
+}
+
+/// The number of vertices in `data`, checked against `layout`.
+fn vertex_count(layout: &VertexLayout, data: &[AttributeData]) -> Result<usize, MeshError> {
+    if layout.attributes.len() != data.len() {
+        return Err(MeshError::AttributeCount { layout: layout.attributes.len(), data: data.len() });
+    }
+    let mut count = None;
+    for (attribute, data) in layout.attributes.iter().zip(data) {
+        if attribute.component_type != data.component_type {
+            return Err(MeshError::ComponentType {
+                attribute: attribute.name.clone(),
+                layout: attribute.component_type,
+                data: data.component_type,
+            });
+        }
+        let components = attribute.components as usize;
+        if data.len % components != 0 {
+            return Err(MeshError::PartialVertex { attribute: attribute.name.clone(), components: data.len });
+        }
+        let found = data.len / components;
+        match count {
+            Some(expected) if expected != found => {
+                return Err(MeshError::VertexCount { attribute: attribute.name.clone(), expected, found });
+            },
+            _ => { count = Some(found); },
+        }
+    }
+    Ok(count.unwrap_or(0))
+}
+
+unsafe fn upload(bytes: &[u8]) -> Buffer {
+    let buffer = Buffer::new();
+    gl::NamedBufferData(buffer.id(), bytes.len() as isize, bytes.as_ptr() as *const _, gl::STATIC_DRAW);
+    buffer
+}
+
+impl Mesh {
+    /// Upload a mesh with one entry of `data` per attribute of `layout`, in the same
+    /// order. Without `indices`, vertices are drawn in order.
+    pub unsafe fn new(layout: &VertexLayout, data: &[AttributeData], indices: Option<&[u32]>) -> Result<Mesh, MeshError> {
+        let vertex_count = vertex_count(layout, data)?;
+        if let Some(&index) = indices.and_then(|indices| indices.iter().find(|&&index| index as usize >= vertex_count)) {
+            return Err(MeshError::IndexOutOfRange { index, vertex_count });
+        }
+
+        let mut vertex_array = VertexArray::new();
+        let vao = vertex_array.id();
+        let (offsets, stride) = layout.offsets();
+
+        if layout.interleaved {
+            let mut bytes = vec![0u8; vertex_count * stride];
+            for ((attribute, data), offset) in layout.attributes.iter().zip(data).zip(&offsets) {
+                let size = attribute.size();
+                for (vertex, source) in data.bytes.chunks_exact(size).enumerate() {
+                    let start = vertex * stride + offset;
+                    bytes[start..start + size].copy_from_slice(source);
+                }
+            }
+            let buffer = upload(&bytes);
+            gl::VertexArrayVertexBuffer(vao, 0, buffer.id(), 0, stride as i32);
+            vertex_array.own_buffer(buffer);
+        } else {
+            for (binding, (attribute, data)) in layout.attributes.iter().zip(data).enumerate() {
+                let buffer = upload(&data.bytes);
+                gl::VertexArrayVertexBuffer(vao, binding as u32, buffer.id(), 0, attribute.size() as i32);
+                vertex_array.own_buffer(buffer);
+            }
+        }
+
+        for (index, (attribute, offset)) in layout.attributes.iter().zip(&offsets).enumerate() {
+            let (binding, relative_offset) = if layout.interleaved { (0, *offset as u32) } else { (index as u32, 0) };
+            let component_type = attribute.component_type.into();
+            if attribute.component_type.is_integer() && !attribute.normalized {
+                gl::VertexArrayAttribIFormat(vao, attribute.location, attribute.components, component_type, relative_offset);
+            } else {
+                let normalized = if attribute.normalized { gl::TRUE } else { gl::FALSE };
+                gl::VertexArrayAttribFormat(vao, attribute.location, attribute.components, component_type, normalized, relative_offset);
+            }
+            gl::VertexArrayAttribBinding(vao, attribute.location, binding);
+            gl::EnableVertexArrayAttrib(vao, attribute.location);
+        }
+
+        if let Some(indices) = indices {
+            let bytes = u32::to_bytes(indices);
+            let buffer = upload(&bytes);
+            gl::VertexArrayElementBuffer(vao, buffer.id());
+            vertex_array.own_buffer(buffer);
+        }
+
+        Ok(Mesh {
+            vertex_array,
+            layout: layout.clone(),
+            vertex_count,
+            index_count: indices.map(<[u32]>::len),
+            primitive: gl::TRIANGLES,
+        })
+    }
+
+    /// Draw as something other than triangles, e.g. `gl::LINES` or `gl::POINTS`.
+    pub fn with_primitive(mut self, primitive: GLenum) -> Mesh {
+        self.primitive = primitive;
+        self
+    }
+
+    pub fn vertex_array(&self) -> &VertexArray {
+        &self.vertex_array
+    }
+
+    pub fn layout(&self) -> &VertexLayout {
+        &self.layout
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertex_count
+    }
+
+    pub fn index_count(&self) -> Option<usize> {
+        self.index_count
+    }
+
+    /// Draw the whole mesh with the currently active program.
+    pub unsafe fn draw(&self) {
+        self.vertex_array.bind();
+        match self.index_count {
+            Some(count) => gl::DrawElements(self.primitive, count as i32, gl::UNSIGNED_INT, std::ptr::null()),
+            None => gl::DrawArrays(self.primitive, 0, self.vertex_count as i32),
+        }
+    }
+
+    /// Activate `shader` and draw the whole mesh with it. The first time this mesh is
+    /// drawn with `shader`, its vertex array is checked against the program's attributes.
+    pub unsafe fn draw_with(&self, shader: &Shader) {
+        shader.activate();
+        shader.check_vertex_array_once(self.vertex_array.id());
+        self.draw();
+    }
+}
@@ -0,0 +1,203 @@
+// Loading models from files into `MeshData`, ready to be processed or uploaded.
+
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::geometry::MeshData;
+use crate::mesh::{Mesh, MeshError};
+
+pub mod gltf;
+pub mod material;
+pub mod obj;
+pub mod ply;
+pub mod stl;
+
+pub use material::{Material, TextureImage};
+
+/// The meshes of one file and the materials they refer to, still on the CPU.
+#[derive(Debug, Clone, Default)]
+pub struct Model {
+    pub meshes: Vec<MeshData>,
+    pub materials: Vec<Material>,
+    /// The scene graph, for formats that have one. Without nodes, every mesh is
+    /// placed at the origin.
+    pub nodes: Vec<Node>,
+    /// The nodes without a parent.
+    pub roots: Vec<usize>,
+}
+
+/// A node of the scene graph, placing meshes relative to its parent.
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub name: String,
+    /// From this node's space to its parent's.
+    pub transform: glm::Mat4,
+    /// Indices into the model's meshes.
+    pub meshes: Vec<usize>,
+    pub children: Vec<usize>,
+}
+
+/// Everything that can go wrong while loading a model.
+#[derive(Debug)]
+pub enum ModelError {
+    /// The file could not be read.
+    Io { path: PathBuf, source: io::Error },
+    /// The file could not be written.
+    Write { path: PathBuf, source: io::Error },
+    /// The file extension is not a format we can load or save.
+    UnknownFormat(PathBuf),
+    /// tobj could not parse an OBJ file.
+    Obj { path: PathBuf, source: tobj::LoadError },
+    /// A glTF file, or a buffer or image it refers to, could not be loaded.
+    Gltf { path: PathBuf, source: ::gltf::Error },
+    /// A PLY or STL file is malformed.
+    Parse { path: PathBuf, message: String },
+    /// A loaded mesh could not be uploaded.
+    Mesh { name: String, source: MeshError },
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelError::Io { path, source } => {
+                write!(f, "Failed to read model {}: {}", path.display(), source)
+            },
+            ModelError::Write { path, source } => {
+                write!(f, "Failed to write model {}: {}", path.display(), source)
+            },
+            ModelError::UnknownFormat(path) => {
+                write!(f, "Unknown model format for file with path: {}", path.display())
+            },
+            ModelError::Obj { path, source } => {
+                write!(f, "Failed to load OBJ file {}: {}", path.display(), source)
+            },
+            ModelError::Gltf { path, source } => {
+                write!(f, "Failed to load glTF file {}: {}", path.display(), source)
+            },
+            ModelError::Parse { path, message } => {
+                write!(f, "Failed to parse {}: {}", path.display(), message)
+            },
+            ModelError::Mesh { name, source } => {
+                write!(f, "Failed to upload mesh \"{}\": {}", name, source)
+            },
+        }
+    }
+}
+
+impl std::error::Error for ModelError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ModelError::Io { source, .. } => Some(source),
+            ModelError::Write { source, .. } => Some(source),
+            ModelError::Obj { source, .. } => Some(source),
+            ModelError::Gltf { source, .. } => Some(source),
+            ModelError::Mesh { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Load a model, picking the format from the file extension.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Model, ModelError> {
+    let path = path.as_ref();
+    let extension = path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("obj") => { obj::load(path) },
+        Some("gltf") | Some("glb") => { gltf::load(path) },
+        Some("ply") => { ply::load(path) },
+        Some("stl") => { stl::load(path) },
+        _ => { Err(ModelError::UnknownFormat(path.to_path_buf())) },
+    }
+}
+
+/// Save the meshes of a model, picking the format from the file extension.
+///
+/// Neither format has a scene graph, so node transforms are baked into the meshes
+/// first (see `Model::baked_meshes`). OBJ keeps every mesh as its own object, while
+/// PLY holds a single mesh, so the meshes are merged. Materials are not written.
+pub fn save<P: AsRef<Path>>(path: P, model: &Model) -> Result<(), ModelError> {
+    let path = path.as_ref();
+    let extension = path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    let meshes = model.baked_meshes();
+    let write = |write: &dyn Fn(&mut BufWriter<File>) -> io::Result<()>| {
+        File::create(path)
+            .map(BufWriter::new)
+            .and_then(|mut writer| {
+                write(&mut writer)?;
+                writer.flush()
+            })
+            .map_err(|source| ModelError::Write { path: path.to_path_buf(), source })
+    };
+    match extension.as_deref() {
+        Some("obj") => { write(&|writer| obj::write(writer, &meshes)) },
+        Some("ply") => {
+            let mut merged = MeshData::default();
+            for mesh in &meshes {
+                merged.append(mesh);
+            }
+            merged.name = path.file_stem().map_or(String::new(), |stem| stem.to_string_lossy().into_owned());
+            write(&|writer| ply::write(writer, &merged))
+        },
+        _ => { Err(ModelError::UnknownFormat(path.to_path_buf())) },
+    }
+}
+
+impl Model {
+    /// The material of `mesh`, which must be one of this model's meshes.
+    pub fn material(&self, mesh: &MeshData) -> Option<&Material> {
+        mesh.material.and_then(|index| self.materials.get(index))
+    }
+
+    /// The transform from each node's space to model space, indexed like `nodes`.
+    pub fn world_transforms(&self) -> Vec<glm::Mat4> {
+        let mut transforms = vec![glm::identity(); self.nodes.len()];
+        let mut stack: Vec<(usize, glm::Mat4)> = self.roots.iter()
+            .map(|&root| (root, glm::identity()))
+            .collect();
+        while let Some((index, parent)) = stack.pop() {
+            let node = &self.nodes[index];
+            transforms[index] = parent * node.transform;
+            stack.extend(node.children.iter().map(|&child| (child, transforms[index])));
+        }
+        transforms
+    }
+
+    /// Every mesh placed in model space, once for each node of the scene that refers
+    /// to it. Without nodes, the meshes are returned as they are.
+    pub fn baked_meshes(&self) -> Vec<MeshData> {
+        if self.nodes.is_empty() {
+            return self.meshes.clone();
+        }
+        let transforms = self.world_transforms();
+        let mut meshes = vec![];
+        // Only the nodes in the scene, parents before their children
+        let mut stack: Vec<usize> = self.roots.iter().rev().copied().collect();
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            stack.extend(node.children.iter().rev());
+            for &index in &node.meshes {
+                let mut mesh = self.meshes[index].transformed(&transforms[node_index]);
+                if !node.name.is_empty() && node.name != mesh.name {
+                    mesh.name = format!("{}.{}", node.name, mesh.name);
+                }
+                meshes.push(mesh);
+            }
+        }
+        meshes
+    }
+
+    /// Upload every mesh, in the same order as `meshes`.
+    pub unsafe fn upload(&self) -> Result<Vec<Mesh>, ModelError> {
+        self.meshes.iter()
+            .map(|mesh| mesh.upload().map_err(|source| ModelError::Mesh { name: mesh.name.clone(), source }))
+            .collect()
+    }
+}
@@ -0,0 +1,324 @@
+use std::{
+    path::Path,
+    sync::Arc,
+};
+
+use gltf::image::Format;
+use gltf::mesh::Mode;
+
+use crate::geometry::MeshData;
+
+use super::material::{Material, TextureImage};
+use super::{Model, ModelError, Node};
+
+/// Load a `.gltf` file with its buffers and images, or a self-contained `.glb`.
+///
+/// Every primitive becomes a mesh of its own, so a node lists one mesh per primitive
+/// of the glTF mesh it refers to. Only the default scene, or the first one, is used.
+/// Texture coordinates are flipped vertically to match OBJ and `TextureImage::upload`,
+/// which put `(0, 0)` in the bottom left. Primitives that aren't triangles, strips or
+/// fans are skipped with a warning.
+pub fn load(path: &Path) -> Result<Model, ModelError> {
+    let (document, buffers, images) = gltf::import(path)
+        .map_err(|source| match source {
+            gltf::Error::Io(source) => ModelError::Io { path: path.to_path_buf(), source },
+            source => ModelError::Gltf { path: path.to_path_buf(), source },
+        })?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let images: Vec<TextureImage> = document.images().zip(images)
+        .map(|(image, data)| TextureImage {
+            path: match image.source() {
+                gltf::image::Source::Uri { uri, .. } if !uri.starts_with("data:") => Some(base_dir.join(uri)),
+                _ => None,
+            },
+            image: Arc::new(to_rgba8(data)),
+        })
+        .collect();
+    let texture = |info: Option<gltf::texture::Texture>| info.map(|texture| images[texture.source().index()].clone());
+
+    let materials = document.materials()
+        .map(|material| {
+            let pbr = material.pbr_metallic_roughness();
+            let [red, green, blue, alpha] = pbr.base_color_factor();
+            Material {
+                name: material.name().unwrap_or_default().to_string(),
+                diffuse: glm::vec3(red, green, blue),
+                alpha,
+                metallic: pbr.metallic_factor(),
+                roughness: pbr.roughness_factor(),
+                emissive: glm::make_vec3(&material.emissive_factor()),
+                diffuse_texture: texture(pbr.base_color_texture().map(|info| info.texture())),
+                metallic_roughness_texture: texture(pbr.metallic_roughness_texture().map(|info| info.texture())),
+                normal_texture: texture(material.normal_texture().map(|info| info.texture())),
+                emissive_texture: texture(material.emissive_texture().map(|info| info.texture())),
+                occlusion_texture: texture(material.occlusion_texture().map(|info| info.texture())),
+                ..Material::default()
+            }
+        })
+        .collect();
+
+    // The meshes made from the primitives of each glTF mesh
+    let mut meshes = vec![];
+    let mut primitive_meshes = vec![];
+    for mesh in document.meshes() {
+        let mut indices = vec![];
+        for (index, primitive) in mesh.primitives().enumerate() {
+            let name = match mesh.name() {
+                Some(name) if mesh.primitives().len() > 1 => format!("{}.{}", name, index),
+                Some(name) => name.to_string(),
+                None => format!("mesh{}.{}", mesh.index(), index),
+            };
+            match mesh_data(&primitive, &buffers, name) {
+                Some(data) => {
+                    indices.push(meshes.len());
+                    meshes.push(data);
+                },
+                None => {
+                    println!("WARNING: skipping {:?} primitive {} of mesh {} in {}",
+                        primitive.mode(), index, mesh.index(), path.display());
+                },
+            }
+        }
+        primitive_meshes.push(indices);
+    }
+
+    let nodes = document.nodes()
+        .map(|node| Node {
+            name: node.name().unwrap_or_default().to_string(),
+            transform: glm::make_mat4(&node.transform().matrix().concat()),
+            meshes: node.mesh().map_or(vec![], |mesh| primitive_meshes[mesh.index()].clone()),
+            children: node.children().map(|child| child.index()).collect(),
+        })
+        .collect();
+    let roots = document.default_scene()
+        .or_else(|| document.scenes().next())
+        .map_or(vec![], |scene| scene.nodes().map(|node| node.index()).collect());
+
+    Ok(Model { meshes, materials, nodes, roots })
+}
+
+/// The triangles of `primitive`, or `None` if it is made of points or lines.
+fn mesh_data(primitive: &gltf::Primitive, buffers: &[gltf::buffer::Data], name: String) -> Option<MeshData> {
+    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+    let positions: Vec<glm::Vec3> = reader.read_positions()
+        .map_or(vec![], |positions| positions.map(|position| glm::make_vec3(&position)).collect());
+    let vertex_indices: Vec<u32> = reader.read_indices()
+        .map_or_else(|| (0..positions.len() as u32).collect(), |indices| indices.into_u32().collect());
+
+    let indices = match primitive.mode() {
+        Mode::Triangles => { vertex_indices },
+        Mode::TriangleStrip => {
+            // Every other triangle is wound the other way around
+            vertex_indices.windows(3).enumerate()
+                .flat_map(|(index, window)| if index % 2 == 0 {
+                    [window[0], window[1], window[2]]
+                } else {
+                    [window[1], window[0], window[2]]
+                })
+                .collect()
+        },
+        Mode::TriangleFan => {
+            vertex_indices.get(1..).unwrap_or_default().windows(2)
+                .flat_map(|window| [vertex_indices[0], window[0], window[1]])
+                .collect()
+        },
+        _ => { return None },
+    };
+
+    Some(MeshData {
+        name,
+        positions,
+        normals: reader.read_normals()
+            .map_or(vec![], |normals| normals.map(|normal| glm::make_vec3(&normal)).collect()),
+        uvs: reader.read_tex_coords(0)
+            .map_or(vec![], |uvs| uvs.into_f32().map(|[u, v]| glm::vec2(u, 1.0 - v)).collect()),
+        colors: reader.read_colors(0)
+            .map_or(vec![], |colors| colors.into_rgba_f32().map(|color| glm::make_vec4(&color)).collect()),
+        // Flipping v mirrors the bitangent, so the handedness in w flips with it
+        tangents: reader.read_tangents()
+            .map_or(vec![], |tangents| tangents.map(|[x, y, z, w]| glm::vec4(x, y, z, -w)).collect()),
+        indices,
+        material: primitive.material().index(),
+    })
+}
+
+/// Expand a decoded image of any format glTF allows to 8-bit RGBA. Missing color
+/// channels are 0 and missing alpha is opaque, 16-bit and float channels are rounded.
+fn to_rgba8(data: gltf::image::Data) -> image::RgbaImage {
+    let (channels, bytes_per_channel) = match data.format {
+        Format::R8                => { (1, 1) },
+        Format::R8G8              => { (2, 1) },
+        Format::R8G8B8            => { (3, 1) },
+        Format::R8G8B8A8          => { (4, 1) },
+        Format::R16               => { (1, 2) },
+        Format::R16G16            => { (2, 2) },
+        Format::R16G16B16         => { (3, 2) },
+        Format::R16G16B16A16      => { (4, 2) },
+        Format::R32G32B32FLOAT    => { (3, 4) },
+        Format::R32G32B32A32FLOAT => { (4, 4) },
+    };
+    let channel = |bytes: &[u8]| -> u8 {
+        match bytes_per_channel {
+            1 => bytes[0],
+            2 => (f32::from(u16::from_ne_bytes([bytes[0], bytes[1]])) / 257.0).round() as u8,
+            _ => (f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]).clamp(0.0, 1.0) * 255.0).round() as u8,
+        }
+    };
+    let pixels = data.pixels.chunks_exact(channels * bytes_per_channel)
+        .flat_map(|pixel| {
+            let mut rgba = [0, 0, 0, 255];
+            for (index, value) in pixel.chunks_exact(bytes_per_channel).enumerate() {
+                rgba[index] = channel(value);
+            }
+            rgba
+        })
+        .collect();
+    image::RgbaImage::from_raw(data.width, data.height, pixels).expect("image data matches its dimensions")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::geometry::tangents;
+
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/gltf").join(name)
+    }
+
+    /// A GLB container holding `json` and a binary chunk.
+    fn glb(json: &str, bin: &[u8]) -> Vec<u8> {
+        let mut json = json.as_bytes().to_vec();
+        json.resize(json.len().div_ceil(4) * 4, b' ');
+        let mut bin = bin.to_vec();
+        bin.resize(bin.len().div_ceil(4) * 4, 0);
+
+        let mut bytes = vec![];
+        bytes.extend_from_slice(b"glTF");
+        bytes.extend_from_slice(&2u32.to_le_bytes());
+        bytes.extend_from_slice(&(12 + 8 + json.len() as u32 + 8 + bin.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(b"JSON");
+        bytes.extend_from_slice(&json);
+        bytes.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(b"BIN\0");
+        bytes.extend_from_slice(&bin);
+        bytes
+    }
+
+    #[test]
+    fn loads_mesh_attributes() {
+        let model = load(&fixture("hierarchy.gltf")).unwrap();
+        assert_eq!(model.meshes.len(), 1);
+        let mesh = &model.meshes[0];
+        assert_eq!(mesh.name, "triangle");
+        assert_eq!(mesh.positions, vec![glm::vec3(0.0, 0.0, 0.0), glm::vec3(1.0, 0.0, 0.0), glm::vec3(0.0, 1.0, 0.0)]);
+        assert_eq!(mesh.normals, vec![glm::vec3(0.0, 0.0, 1.0); 3]);
+        // Flipped so (0, 0) is the bottom left
+        assert_eq!(mesh.uvs, vec![glm::vec2(0.0, 1.0), glm::vec2(1.0, 1.0), glm::vec2(0.0, 0.0)]);
+        assert!(mesh.colors.is_empty());
+        assert_eq!(mesh.indices, vec![0, 1, 2]);
+        assert_eq!(mesh.material, Some(0));
+    }
+
+    #[test]
+    fn tangent_handedness_follows_flipped_uvs() {
+        let model = load(&fixture("hierarchy.gltf")).unwrap();
+        let mesh = &model.meshes[0];
+        // The file stores w = 1 for v running along +y, which the flip turns around
+        assert_eq!(mesh.tangents, vec![glm::vec4(1.0, 0.0, 0.0, -1.0); 3]);
+
+        let generated = tangents::generate(mesh).unwrap();
+        for (loaded, generated) in mesh.tangents.iter().zip(&generated.tangents) {
+            assert!((loaded - generated).norm() < 1e-5, "{:?} != {:?}", loaded, generated);
+        }
+    }
+
+    #[test]
+    fn loads_node_hierarchy() {
+        let model = load(&fixture("hierarchy.gltf")).unwrap();
+        assert_eq!(model.roots, vec![0]);
+        assert_eq!(model.nodes[0].name, "parent");
+        assert_eq!(model.nodes[0].children, vec![1]);
+        assert!(model.nodes[0].meshes.is_empty());
+        assert_eq!(model.nodes[1].meshes, vec![0]);
+
+        let world = model.world_transforms();
+        let corner = world[1] * glm::vec4(1.0, 0.0, 0.0, 1.0);
+        assert_eq!(corner, glm::vec4(3.0, 2.0, 3.0, 1.0));
+    }
+
+    #[test]
+    fn loads_materials_with_external_and_embedded_images() {
+        let model = load(&fixture("hierarchy.gltf")).unwrap();
+        let material = model.material(&model.meshes[0]).unwrap();
+        assert_eq!(material.name, "painted");
+        assert_eq!(material.diffuse, glm::vec3(1.0, 0.5, 0.25));
+        assert_eq!(material.alpha, 0.75);
+        assert_eq!(material.metallic, 0.25);
+        assert_eq!(material.roughness, 0.5);
+        assert_eq!(material.emissive, glm::vec3(0.1, 0.2, 0.3));
+
+        let base_color = material.diffuse_texture.as_ref().unwrap();
+        assert_eq!(base_color.path.as_deref(), Some(fixture("checker.png").as_path()));
+        assert_eq!(base_color.image.dimensions(), (2, 2));
+        assert_eq!(base_color.image.get_pixel(1, 0).0, [0, 255, 0, 255]);
+
+        let normal = material.normal_texture.as_ref().unwrap();
+        assert_eq!(normal.path, None);
+        assert_eq!(normal.image.get_pixel(0, 0).0, [128, 128, 255, 255]);
+    }
+
+    #[test]
+    fn loads_glb_with_buffer_view_image_and_strips() {
+        let png = std::fs::read(fixture("checker.png")).unwrap();
+        let mut bin: Vec<u8> = [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        bin.extend_from_slice(&png);
+        let json = format!(r#"{{
+            "asset": {{ "version": "2.0" }},
+            "scenes": [{{ "nodes": [0] }}],
+            "nodes": [{{ "mesh": 0, "matrix": [1,0,0,0, 0,1,0,0, 0,0,1,0, 5,0,0,1] }}],
+            "meshes": [{{ "primitives": [
+                {{ "attributes": {{ "POSITION": 0 }}, "mode": 5, "material": 0 }},
+                {{ "attributes": {{ "POSITION": 0 }}, "mode": 0 }}
+            ] }}],
+            "materials": [{{ "pbrMetallicRoughness": {{ "baseColorTexture": {{ "index": 0 }} }} }}],
+            "textures": [{{ "source": 0 }}],
+            "images": [{{ "bufferView": 1, "mimeType": "image/png" }}],
+            "accessors": [{{ "bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3",
+                            "min": [0, 0, 0], "max": [1, 1, 0] }}],
+            "bufferViews": [
+                {{ "buffer": 0, "byteOffset": 0, "byteLength": 48 }},
+                {{ "buffer": 0, "byteOffset": 48, "byteLength": {} }}
+            ],
+            "buffers": [{{ "byteLength": {} }}]
+        }}"#, png.len(), bin.len());
+
+        let path = std::env::temp_dir().join(format!("gloom-rs-strip-{}.glb", std::process::id()));
+        std::fs::write(&path, glb(&json, &bin)).unwrap();
+        let model = load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let model = model.unwrap();
+
+        // The point primitive is skipped
+        assert_eq!(model.meshes.len(), 1);
+        assert_eq!(model.nodes[0].meshes, vec![0]);
+        assert_eq!(model.meshes[0].indices, vec![0, 1, 2, 2, 1, 3]);
+        assert_eq!(model.world_transforms()[0] * glm::vec4(0.0, 0.0, 0.0, 1.0), glm::vec4(5.0, 0.0, 0.0, 1.0));
+
+        let texture = model.materials[0].diffuse_texture.as_ref().unwrap();
+        assert_eq!(texture.path, None);
+        assert_eq!(texture.image.get_pixel(0, 1).0, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        assert!(matches!(load(&fixture("missing.gltf")), Err(ModelError::Io { .. }) | Err(ModelError::Gltf { .. })));
+    }
+}
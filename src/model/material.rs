@@ -0,0 +1,120 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::gl_object::Texture;
+
+/// An image used by a material, decoded to 8-bit RGBA. Materials that use the same
+/// file share one copy.
+#[derive(Debug, Clone)]
+pub struct TextureImage {
+    /// The file the image was read from, resolved relative to the model. `None` for
+    /// images embedded in the model file.
+    pub path: Option<PathBuf>,
+    pub image: Arc<image::RgbaImage>,
+}
+
+/// How a surface looks, as authored in the file it was loaded from. Colors are
+/// linear factors that multiply the matching texture, if there is one.
+///
+/// OBJ materials fill in the Phong terms, glTF materials the metallic-roughness terms,
+/// with the base color as `diffuse` and `alpha`.
+#[derive(Debug, Clone)]
+pub struct Material {
+    pub name: String,
+    pub ambient: glm::Vec3,
+    pub diffuse: glm::Vec3,
+    pub specular: glm::Vec3,
+    /// The specular exponent.
+    pub shininess: f32,
+    /// Opacity, from 0 for fully transparent to 1 for opaque.
+    pub alpha: f32,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive: glm::Vec3,
+    pub ambient_texture: Option<TextureImage>,
+    pub diffuse_texture: Option<TextureImage>,
+    pub specular_texture: Option<TextureImage>,
+    pub normal_texture: Option<TextureImage>,
+    pub shininess_texture: Option<TextureImage>,
+    pub alpha_texture: Option<TextureImage>,
+    /// Metalness in the blue channel and roughness in the green channel.
+    pub metallic_roughness_texture: Option<TextureImage>,
+    pub emissive_texture: Option<TextureImage>,
+    pub occlusion_texture: Option<TextureImage>,
+}
+
+impl Default for Material {
+    fn default() -> Material {
+        Material {
+            name: String::new(),
+            ambient: glm::zero(),
+            diffuse: glm::vec3(0.8, 0.8, 0.8),
+            specular: glm::zero(),
+            shininess: 0.0,
+            alpha: 1.0,
+            metallic: 0.0,
+            roughness: 1.0,
+            emissive: glm::zero(),
+            ambient_texture: None,
+            diffuse_texture: None,
+            specular_texture: None,
+            normal_texture: None,
+            shininess_texture: None,
+            alpha_texture: None,
+            metallic_roughness_texture: None,
+            emissive_texture: None,
+            occlusion_texture: None,
+        }
+    }
+}
+
+/// Reads each texture file once, no matter how many materials refer to it.
+#[derive(Default)]
+pub struct TextureLoader {
+    loaded: HashMap<PathBuf, Option<TextureImage>>,
+}
+
+impl TextureLoader {
+    /// Load `path`, relative to `base_dir` unless it is absolute. A missing or broken
+    /// texture only prints a warning, the material is still usable without it.
+    pub fn load(&mut self, base_dir: &Path, path: &str) -> Option<TextureImage> {
+        let path = base_dir.join(path);
+        self.loaded.entry(path.clone())
+            .or_insert_with(|| match image::open(&path) {
+                Ok(image) => Some(TextureImage { image: Arc::new(image.into_rgba8()), path: Some(path) }),
+                Err(error) => {
+                    println!("WARNING: could not load texture {}: {}", path.display(), error);
+                    None
+                },
+            })
+            .clone()
+    }
+}
+
+impl TextureImage {
+    /// Upload as a mipmapped, repeating 2D texture.
+    ///
+    /// Images are stored top row first, while OpenGL expects the bottom row first, so
+    /// the rows are flipped to make `(0, 0)` the bottom left as in OBJ texture coordinates.
+    pub unsafe fn upload(&self) -> Texture {
+        let (width, height) = self.image.dimensions();
+        let flipped = image::imageops::flip_vertical(&*self.image);
+        let levels = 32 - width.max(height).max(1).leading_zeros();
+
+        let texture = Texture::new(gl::TEXTURE_2D);
+        gl::TextureStorage2D(texture.id(), levels as i32, gl::RGBA8, width as i32, height as i32);
+        gl::TextureSubImage2D(
+            texture.id(), 0, 0, 0, width as i32, height as i32,
+            gl::RGBA, gl::UNSIGNED_BYTE, flipped.as_ptr() as *const _,
+        );
+        gl::GenerateTextureMipmap(texture.id());
+        gl::TextureParameteri(texture.id(), gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+        gl::TextureParameteri(texture.id(), gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
+        gl::TextureParameteri(texture.id(), gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR as i32);
+        gl::TextureParameteri(texture.id(), gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+        texture
+    }
+}
@@ -0,0 +1,218 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Write},
+    path::Path,
+};
+
+use crate::geometry::MeshData;
+
+use super::material::{Material, TextureLoader};
+use super::{Model, ModelError};
+
+/// One mesh per object or group in the file, triangulated, with a single index per
+/// vertex. Vertex colors given after the position (`v x y z r g b`) become opaque
+/// `colors`; attributes the file doesn't have are left empty.
+///
+/// Materials come from the `.mtl` files the OBJ refers to, with texture paths resolved
+/// relative to the OBJ. A missing `.mtl` or texture only prints a warning.
+pub fn load(path: &Path) -> Result<Model, ModelError> {
+    let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let file = File::open(path).map_err(|source| ModelError::Io { path: path.to_path_buf(), source })?;
+    let (models, materials) = tobj::load_obj_buf(&mut BufReader::new(file), &tobj::GPU_LOAD_OPTIONS, |mtl_path| {
+        tobj::load_mtl(base_dir.join(mtl_path))
+    })
+    .map_err(|source| ModelError::Obj { path: path.to_path_buf(), source })?;
+
+    let materials = materials.unwrap_or_else(|error| {
+        println!("WARNING: could not load the materials of {}: {}", path.display(), error);
+        vec![]
+    });
+    let mut textures = TextureLoader::default();
+    let materials: Vec<Material> = materials.into_iter()
+        .map(|material| convert_material(material, base_dir, &mut textures))
+        .collect();
+
+    let meshes = models.into_iter()
+        .map(|model| {
+            let mut mesh = mesh_data(model);
+            // Ignore references to materials that failed to load
+            mesh.material = mesh.material.filter(|&index| index < materials.len());
+            mesh
+        })
+        .collect();
+    Ok(Model { meshes, materials, ..Model::default() })
+}
+
+/// The file name in a texture statement, skipping options such as `-bm 0.5`.
+fn texture_file(statement: &str) -> Option<&str> {
+    let statement = statement.trim();
+    if statement.is_empty() {
+        None
+    } else if statement.starts_with('-') {
+        statement.split_whitespace().last()
+    } else {
+        Some(statement)
+    }
+}
+
+fn convert_material(material: tobj::Material, base_dir: &Path, textures: &mut TextureLoader) -> Material {
+    let mut texture = |statement: &str| texture_file(statement).and_then(|file| textures.load(base_dir, file));
+    Material {
+        ambient_texture: texture(&material.ambient_texture),
+        diffuse_texture: texture(&material.diffuse_texture),
+        specular_texture: texture(&material.specular_texture),
+        normal_texture: texture(&material.normal_texture),
+        shininess_texture: texture(&material.shininess_texture),
+        alpha_texture: texture(&material.dissolve_texture),
+        name: material.name,
+        ambient: glm::make_vec3(&material.ambient),
+        diffuse: glm::make_vec3(&material.diffuse),
+        specular: glm::make_vec3(&material.specular),
+        shininess: material.shininess,
+        alpha: material.dissolve,
+        ..Material::default()
+    }
+}
+
+fn mesh_data(model: tobj::Model) -> MeshData {
+    let mesh = model.mesh;
+    MeshData {
+        name: model.name,
+        positions: mesh.positions.chunks_exact(3).map(glm::make_vec3).collect(),
+        normals: mesh.normals.chunks_exact(3).map(glm::make_vec3).collect(),
+        uvs: mesh.texcoords.chunks_exact(2).map(glm::make_vec2).collect(),
+        colors: mesh.vertex_color.chunks_exact(3).map(|color| glm::vec4(color[0], color[1], color[2], 1.0)).collect(),
+        tangents: vec![],
+        indices: mesh.indices,
+        material: mesh.material_id,
+    }
+}
+
+/// Write `meshes` as one object each. Colors are written after the position
+/// (`v x y z r g b`), without alpha, which most tools that read OBJ understand. Readers
+/// expect either every vertex or none to have a color, so if any mesh has colors, the
+/// vertices of the others are written white.
+pub fn write<W: Write>(writer: &mut W, meshes: &[MeshData]) -> io::Result<()> {
+    let colored = meshes.iter().any(|mesh| !mesh.colors.is_empty());
+    // OBJ indices are one-based and count every `v`, `vt` or `vn` before them in the file
+    let (mut positions, mut uvs, mut normals) = (1, 1, 1);
+    for (index, mesh) in meshes.iter().enumerate() {
+        if mesh.name.is_empty() {
+            writeln!(writer, "o mesh{}", index)?;
+        } else {
+            writeln!(writer, "o {}", mesh.name)?;
+        }
+        for (vertex, position) in mesh.positions.iter().enumerate() {
+            write!(writer, "v {} {} {}", position.x, position.y, position.z)?;
+            if let Some(color) = mesh.colors.get(vertex) {
+                write!(writer, " {} {} {}", color.x, color.y, color.z)?;
+            } else if colored {
+                write!(writer, " 1 1 1")?;
+            }
+            writeln!(writer)?;
+        }
+        for uv in &mesh.uvs {
+            writeln!(writer, "vt {} {}", uv.x, uv.y)?;
+        }
+        for normal in &mesh.normals {
+            writeln!(writer, "vn {} {} {}", normal.x, normal.y, normal.z)?;
+        }
+
+        let has_uvs = !mesh.uvs.is_empty();
+        let has_normals = !mesh.normals.is_empty();
+        for triangle in mesh.triangles() {
+            write!(writer, "f")?;
+            for index in triangle {
+                let index = index as usize;
+                write!(writer, " {}", index + positions)?;
+                match (has_uvs, has_normals) {
+                    (false, false) => {},
+                    (true, false)  => { write!(writer, "/{}", index + uvs)? },
+                    (false, true)  => { write!(writer, "//{}", index + normals)? },
+                    (true, true)   => { write!(writer, "/{}/{}", index + uvs, index + normals)? },
+                }
+            }
+            writeln!(writer)?;
+        }
+        positions += mesh.positions.len();
+        uvs += mesh.uvs.len();
+        normals += mesh.normals.len();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{self, Model, Node};
+
+    use super::*;
+
+    fn quad() -> MeshData {
+        MeshData {
+            name: "quad".to_string(),
+            positions: vec![glm::vec3(0.0, 0.0, 0.0), glm::vec3(1.0, 0.0, 0.0), glm::vec3(1.0, 1.0, 0.0), glm::vec3(0.0, 1.0, 0.0)],
+            normals: vec![glm::vec3(0.0, 0.0, 1.0); 4],
+            uvs: vec![glm::vec2(0.0, 0.0), glm::vec2(1.0, 0.0), glm::vec2(1.0, 1.0), glm::vec2(0.0, 1.0)],
+            colors: vec![glm::vec4(1.0, 0.0, 0.0, 1.0), glm::vec4(0.0, 1.0, 0.0, 1.0), glm::vec4(0.0, 0.0, 1.0, 1.0), glm::vec4(0.1, 0.2, 0.3, 1.0)],
+            indices: vec![0, 1, 2, 0, 2, 3],
+            ..MeshData::default()
+        }
+    }
+
+    fn round_trip(model: &Model, name: &str) -> Model {
+        let path = std::env::temp_dir().join(format!("gloom-rs-{}-{}.obj", name, std::process::id()));
+        model::save(&path, model).unwrap();
+        let loaded = model::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        loaded.unwrap()
+    }
+
+    #[test]
+    fn round_trips_every_attribute() {
+        let model = Model { meshes: vec![quad()], ..Model::default() };
+        assert_eq!(round_trip(&model, "attributes").meshes, vec![quad()]);
+    }
+
+    #[test]
+    fn round_trips_meshes_with_different_attributes() {
+        let bare = MeshData {
+            name: "bare".to_string(),
+            positions: vec![glm::vec3(2.0, 0.0, 0.0), glm::vec3(3.0, 0.0, 0.0), glm::vec3(2.0, 1.0, 0.0)],
+            indices: vec![0, 1, 2],
+            ..MeshData::default()
+        };
+        let model = Model { meshes: vec![bare.clone(), quad()], ..Model::default() };
+        let loaded = round_trip(&model, "mixed");
+
+        assert_eq!(loaded.meshes.len(), 2);
+        assert_eq!(loaded.meshes[0].positions, bare.positions);
+        assert_eq!(loaded.meshes[0].indices, bare.indices);
+        assert!(loaded.meshes[0].normals.is_empty());
+        assert!(loaded.meshes[0].uvs.is_empty());
+        // Written white, since the other mesh has colors
+        assert_eq!(loaded.meshes[0].colors, vec![glm::vec4(1.0, 1.0, 1.0, 1.0); 3]);
+        assert_eq!(loaded.meshes[1], quad());
+    }
+
+    #[test]
+    fn bakes_node_transforms() {
+        let mirror = glm::scaling(&glm::vec3(-1.0, 1.0, 1.0));
+        let model = Model {
+            meshes: vec![quad()],
+            nodes: vec![Node { name: "mirrored".to_string(), transform: mirror, meshes: vec![0], children: vec![] }],
+            roots: vec![0],
+            ..Model::default()
+        };
+        let mesh = &round_trip(&model, "baked").meshes[0];
+
+        assert_eq!(mesh.name, "mirrored.quad");
+        assert!(mesh.positions.contains(&glm::vec3(-1.0, 0.0, 0.0)));
+        // The normal keeps pointing the same way, and the winding is flipped so it is
+        // still counter-clockwise seen from the front
+        assert_eq!(mesh.normals[0], glm::vec3(0.0, 0.0, 1.0));
+        for [a, b, c] in mesh.triangles() {
+            let [a, b, c] = [a, b, c].map(|index| mesh.positions[index as usize]);
+            assert!((b - a).cross(&(c - a)).z > 0.0);
+        }
+    }
+}
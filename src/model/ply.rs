@@ -0,0 +1,531 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+    str::SplitAsciiWhitespace,
+};
+
+use crate::geometry::{normals, MeshData};
+
+use super::{Model, ModelError};
+
+/// The type of a property, or of the count or items of a list property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Scalar> {
+        match name {
+            "char" | "int8"     => { Some(Scalar::I8)  },
+            "uchar" | "uint8"   => { Some(Scalar::U8)  },
+            "short" | "int16"   => { Some(Scalar::I16) },
+            "ushort" | "uint16" => { Some(Scalar::U16) },
+            "int" | "int32"     => { Some(Scalar::I32) },
+            "uint" | "uint32"   => { Some(Scalar::U32) },
+            "float" | "float32" => { Some(Scalar::F32) },
+            "double" | "float64" => { Some(Scalar::F64) },
+            _ => { None },
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Scalar::I8  => { "char"   },
+            Scalar::U8  => { "uchar"  },
+            Scalar::I16 => { "short"  },
+            Scalar::U16 => { "ushort" },
+            Scalar::I32 => { "int"    },
+            Scalar::U32 => { "uint"   },
+            Scalar::F32 => { "float"  },
+            Scalar::F64 => { "double" },
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8   => { 1 },
+            Scalar::I16 | Scalar::U16 => { 2 },
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => { 4 },
+            Scalar::F64 => { 8 },
+        }
+    }
+
+    /// The value colors are stored as at full intensity.
+    fn color_scale(self) -> f64 {
+        match self {
+            Scalar::U8  => { 255.0 },
+            Scalar::U16 => { 65535.0 },
+            _ => { 1.0 },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Property {
+    Scalar { name: String, kind: Scalar },
+    List { name: String, count: Scalar, item: Scalar },
+}
+
+impl Property {
+    fn name(&self) -> &str {
+        match self {
+            Property::Scalar { name, .. } | Property::List { name, .. } => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+/// The header, and the offset of the first byte after it.
+fn parse_header(bytes: &[u8]) -> Result<(Encoding, Vec<Element>, usize), String> {
+    const END: &[u8] = b"end_header";
+    let end = bytes.windows(END.len())
+        .position(|window| window == END)
+        .ok_or("no end_header line")?;
+    let body = bytes[end..].iter()
+        .position(|&byte| byte == b'\n')
+        .map_or(bytes.len(), |newline| end + newline + 1);
+    let header = std::str::from_utf8(&bytes[..end]).map_err(|_| "header is not text")?;
+
+    let mut lines = header.lines();
+    if lines.next().map(str::trim) != Some("ply") {
+        return Err("not a PLY file".to_string());
+    }
+    let mut encoding = None;
+    let mut elements: Vec<Element> = vec![];
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["format", format, _version] => {
+                encoding = Some(match *format {
+                    "ascii" => { Encoding::Ascii },
+                    "binary_little_endian" => { Encoding::LittleEndian },
+                    "binary_big_endian" => { Encoding::BigEndian },
+                    _ => { return Err(format!("unknown format \"{}\"", format)) },
+                });
+            },
+            ["element", name, count] => {
+                let count = count.parse().map_err(|_| format!("bad element count \"{}\"", count))?;
+                elements.push(Element { name: name.to_string(), count, properties: vec![] });
+            },
+            ["property", "list", count, item, name] => {
+                let property = Property::List {
+                    name: name.to_string(),
+                    count: Scalar::parse(count).ok_or_else(|| format!("unknown type \"{}\"", count))?,
+                    item: Scalar::parse(item).ok_or_else(|| format!("unknown type \"{}\"", item))?,
+                };
+                elements.last_mut().ok_or("property before any element")?.properties.push(property);
+            },
+            ["property", kind, name] => {
+                let property = Property::Scalar {
+                    name: name.to_string(),
+                    kind: Scalar::parse(kind).ok_or_else(|| format!("unknown type \"{}\"", kind))?,
+                };
+                elements.last_mut().ok_or("property before any element")?.properties.push(property);
+            },
+            ["comment", ..] | ["obj_info", ..] | [] => {},
+            _ => { return Err(format!("unexpected header line \"{}\"", line)) },
+        }
+    }
+    Ok((encoding.ok_or("no format line")?, elements, body))
+}
+
+/// Reads values of the body, in either encoding.
+enum Body<'a> {
+    Ascii(SplitAsciiWhitespace<'a>),
+    Binary { bytes: &'a [u8], big_endian: bool },
+}
+
+impl<'a> Body<'a> {
+    fn read(&mut self, kind: Scalar) -> Result<f64, String> {
+        match self {
+            Body::Ascii(words) => {
+                let word = words.next().ok_or("file ends before all elements were read")?;
+                word.parse().map_err(|_| format!("bad number \"{}\"", word))
+            },
+            Body::Binary { bytes, big_endian } => {
+                if bytes.len() < kind.size() {
+                    return Err("file ends before all elements were read".to_string());
+                }
+                let (value, rest) = bytes.split_at(kind.size());
+                *bytes = rest;
+                let mut buffer = [0u8; 8];
+                buffer[..value.len()].copy_from_slice(value);
+                if *big_endian {
+                    buffer[..value.len()].reverse();
+                }
+                let [b0, b1, b2, b3, ..] = buffer;
+                Ok(match kind {
+                    Scalar::I8  => { f64::from(b0 as i8) },
+                    Scalar::U8  => { f64::from(b0) },
+                    Scalar::I16 => { f64::from(i16::from_le_bytes([b0, b1])) },
+                    Scalar::U16 => { f64::from(u16::from_le_bytes([b0, b1])) },
+                    Scalar::I32 => { f64::from(i32::from_le_bytes([b0, b1, b2, b3])) },
+                    Scalar::U32 => { f64::from(u32::from_le_bytes([b0, b1, b2, b3])) },
+                    Scalar::F32 => { f64::from(f32::from_le_bytes([b0, b1, b2, b3])) },
+                    Scalar::F64 => { f64::from_le_bytes(buffer) },
+                })
+            },
+        }
+    }
+}
+
+/// One value per property in `values`, with list properties read into `lists` instead.
+fn read_element(body: &mut Body, element: &Element, values: &mut Vec<f64>, lists: &mut Vec<Vec<f64>>) -> Result<(), String> {
+    values.clear();
+    lists.clear();
+    for property in &element.properties {
+        match property {
+            Property::Scalar { kind, .. } => {
+                values.push(body.read(*kind)?);
+            },
+            Property::List { count, item, .. } => {
+                let count = body.read(*count)? as usize;
+                let list = (0..count).map(|_| body.read(*item)).collect::<Result<_, _>>()?;
+                values.push(f64::NAN);
+                lists.push(list);
+            },
+        }
+    }
+    Ok(())
+}
+
+/// The index of the first property of `element` with one of `names`.
+fn find(element: &Element, names: &[&str]) -> Option<usize> {
+    element.properties.iter().position(|property| names.contains(&property.name()))
+}
+
+fn parse(bytes: &[u8], name: String) -> Result<MeshData, String> {
+    let (encoding, elements, body_offset) = parse_header(bytes)?;
+    let mut body = match encoding {
+        Encoding::Ascii => {
+            let text = std::str::from_utf8(&bytes[body_offset..]).map_err(|_| "ASCII body is not text")?;
+            Body::Ascii(text.split_ascii_whitespace())
+        },
+        Encoding::LittleEndian => { Body::Binary { bytes: &bytes[body_offset..], big_endian: false } },
+        Encoding::BigEndian => { Body::Binary { bytes: &bytes[body_offset..], big_endian: true } },
+    };
+
+    let mut mesh = MeshData { name, ..MeshData::default() };
+    let mut values = vec![];
+    let mut lists = vec![];
+    for element in &elements {
+        match element.name.as_str() {
+            "vertex" => {
+                let position = [find(element, &["x"]), find(element, &["y"]), find(element, &["z"])];
+                let normal = [find(element, &["nx"]), find(element, &["ny"]), find(element, &["nz"])];
+                let uv = [
+                    find(element, &["u", "s", "texture_u", "texture_s"]),
+                    find(element, &["v", "t", "texture_v", "texture_t"]),
+                ];
+                let color = [find(element, &["red"]), find(element, &["green"]), find(element, &["blue"])];
+                let alpha = find(element, &["alpha"]);
+                let [Some(x), Some(y), Some(z)] = position else {
+                    return Err("vertices have no x, y and z properties".to_string());
+                };
+                let color_scale = |index: usize| match &element.properties[index] {
+                    Property::Scalar { kind, .. } => kind.color_scale(),
+                    Property::List { .. } => 1.0,
+                };
+
+                for _ in 0..element.count {
+                    read_element(&mut body, element, &mut values, &mut lists)?;
+                    mesh.positions.push(glm::vec3(values[x] as f32, values[y] as f32, values[z] as f32));
+                    if let [Some(x), Some(y), Some(z)] = normal {
+                        mesh.normals.push(glm::vec3(values[x] as f32, values[y] as f32, values[z] as f32));
+                    }
+                    if let [Some(u), Some(v)] = uv {
+                        mesh.uvs.push(glm::vec2(values[u] as f32, values[v] as f32));
+                    }
+                    if let [Some(red), Some(green), Some(blue)] = color {
+                        let channel = |index: usize| (values[index] / color_scale(index)) as f32;
+                        let alpha = alpha.map_or(1.0, channel);
+                        mesh.colors.push(glm::vec4(channel(red), channel(green), channel(blue), alpha));
+                    }
+                }
+            },
+            "face" => {
+                let Some(indices) = find(element, &["vertex_indices", "vertex_index"]) else {
+                    return Err("faces have no vertex_indices property".to_string());
+                };
+                if !matches!(element.properties[indices], Property::List { .. }) {
+                    return Err(format!("face property \"{}\" is not a list", element.properties[indices].name()));
+                }
+                // Position among the list properties
+                let list = element.properties[..indices].iter()
+                    .filter(|property| matches!(property, Property::List { .. }))
+                    .count();
+                for _ in 0..element.count {
+                    read_element(&mut body, element, &mut values, &mut lists)?;
+                    let face = &lists[list];
+                    // Polygons are split into a fan of triangles
+                    for corner in 2..face.len() {
+                        mesh.indices.extend_from_slice(&[face[0] as u32, face[corner - 1] as u32, face[corner] as u32]);
+                    }
+                }
+            },
+            _ => {
+                for _ in 0..element.count {
+                    read_element(&mut body, element, &mut values, &mut lists)?;
+                }
+            },
+        }
+    }
+
+    if let Some(&index) = mesh.indices.iter().find(|&&index| index as usize >= mesh.positions.len()) {
+        return Err(format!("face refers to vertex {} of {}", index, mesh.positions.len()));
+    }
+    Ok(mesh)
+}
+
+/// Load an ASCII or binary PLY file as a single mesh named after the file.
+///
+/// Reads positions, normals, texture coordinates and colors from the `vertex` element
+/// and polygons from the `face` element, split into triangles. Integer colors are
+/// scaled to [0, 1]. Without normals, flat normals are computed from the faces.
+pub fn load(path: &Path) -> Result<Model, ModelError> {
+    let bytes = fs::read(path).map_err(|source| ModelError::Io { path: path.to_path_buf(), source })?;
+    let name = path.file_stem().map_or(String::new(), |stem| stem.to_string_lossy().into_owned());
+    let mut mesh = parse(&bytes, name)
+        .map_err(|message| ModelError::Parse { path: path.to_path_buf(), message })?;
+    if mesh.normals.is_empty() && !mesh.indices.is_empty() {
+        mesh = normals::flat(&mesh);
+    }
+    Ok(Model { meshes: vec![mesh], ..Model::default() })
+}
+
+/// Write `mesh` as a binary little-endian PLY file. Positions, normals and texture
+/// coordinates are stored as floats, and colors as `uchar`s, the type most tools expect.
+pub fn write<W: Write>(writer: &mut W, mesh: &MeshData) -> io::Result<()> {
+    writeln!(writer, "ply")?;
+    writeln!(writer, "format binary_little_endian 1.0")?;
+    if !mesh.name.is_empty() {
+        writeln!(writer, "comment {}", mesh.name)?;
+    }
+    writeln!(writer, "element vertex {}", mesh.vertex_count())?;
+    let mut properties = vec!["x", "y", "z"];
+    if !mesh.normals.is_empty() {
+        properties.extend_from_slice(&["nx", "ny", "nz"]);
+    }
+    if !mesh.uvs.is_empty() {
+        properties.extend_from_slice(&["u", "v"]);
+    }
+    for property in properties {
+        writeln!(writer, "property {} {}", Scalar::F32.name(), property)?;
+    }
+    if !mesh.colors.is_empty() {
+        for property in ["red", "green", "blue", "alpha"] {
+            writeln!(writer, "property {} {}", Scalar::U8.name(), property)?;
+        }
+    }
+    writeln!(writer, "element face {}", mesh.triangle_count())?;
+    writeln!(writer, "property list {} {} vertex_indices", Scalar::U8.name(), Scalar::U32.name())?;
+    writeln!(writer, "end_header")?;
+
+    let mut bytes = vec![];
+    for vertex in 0..mesh.vertex_count() {
+        let mut floats: Vec<f32> = mesh.positions[vertex].iter().copied().collect();
+        if let Some(normal) = mesh.normals.get(vertex) {
+            floats.extend(normal.iter());
+        }
+        if let Some(uv) = mesh.uvs.get(vertex) {
+            floats.extend(uv.iter());
+        }
+        bytes.extend(floats.iter().flat_map(|value| value.to_le_bytes()));
+        if let Some(color) = mesh.colors.get(vertex) {
+            bytes.extend(color.iter().map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8));
+        }
+    }
+    for triangle in mesh.triangles() {
+        bytes.push(3);
+        bytes.extend(triangle.iter().flat_map(|index| index.to_le_bytes()));
+    }
+    writer.write_all(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{self, Model};
+
+    use super::*;
+
+    fn pyramid() -> MeshData {
+        MeshData {
+            name: "pyramid".to_string(),
+            positions: vec![glm::vec3(0.0, 0.0, 0.0), glm::vec3(1.0, 0.0, 0.0), glm::vec3(0.0, 1.0, 0.0), glm::vec3(0.25, 0.25, -1.5)],
+            normals: vec![glm::vec3(0.0, 0.0, 1.0), glm::vec3(1.0, 0.0, 0.0), glm::vec3(0.0, 1.0, 0.0), glm::vec3(0.0, 0.0, -1.0)],
+            uvs: vec![glm::vec2(0.0, 0.0), glm::vec2(1.0, 0.0), glm::vec2(0.0, 1.0), glm::vec2(0.5, 0.5)],
+            colors: vec![glm::vec4(1.0, 0.0, 0.0, 1.0), glm::vec4(0.0, 1.0, 0.0, 0.5), glm::vec4(0.0, 0.0, 1.0, 0.0), glm::vec4(0.2, 0.4, 0.6, 0.8)],
+            indices: vec![0, 2, 1, 0, 1, 3, 1, 2, 3, 2, 0, 3],
+            ..MeshData::default()
+        }
+    }
+
+    const ASCII: &str = "ply
+format ascii 1.0
+comment a unit quad
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 255 0 51
+1 0 0 0 255 102
+1 1 0 0 0 255
+0 1 0 255 255 255
+4 0 1 2 3
+";
+
+    fn assert_colors_near(colors: &[glm::Vec4], expected: &[glm::Vec4]) {
+        assert_eq!(colors.len(), expected.len());
+        for (color, expected) in colors.iter().zip(expected) {
+            assert!(glm::comp_max(&(color - expected).abs()) < 1e-6, "{:?} != {:?}", color, expected);
+        }
+    }
+
+    #[test]
+    fn parses_ascii_and_splits_polygons() {
+        let mesh = parse(ASCII.as_bytes(), "quad".to_string()).unwrap();
+        assert_eq!(mesh.positions, vec![glm::vec3(0.0, 0.0, 0.0), glm::vec3(1.0, 0.0, 0.0), glm::vec3(1.0, 1.0, 0.0), glm::vec3(0.0, 1.0, 0.0)]);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert!(mesh.normals.is_empty() && mesh.uvs.is_empty());
+        // uchar colors are scaled to [0, 1], and opaque without alpha
+        assert_colors_near(&mesh.colors, &[
+            glm::vec4(1.0, 0.0, 0.2, 1.0),
+            glm::vec4(0.0, 1.0, 0.4, 1.0),
+            glm::vec4(0.0, 0.0, 1.0, 1.0),
+            glm::vec4(1.0, 1.0, 1.0, 1.0),
+        ]);
+    }
+
+    #[test]
+    fn parses_big_endian() {
+        let mut bytes = b"ply
+format binary_big_endian 1.0
+element vertex 3
+property float x
+property float y
+property float z
+property ushort red
+property ushort green
+property ushort blue
+property ushort alpha
+element face 1
+property uchar flags
+property list uchar uint vertex_indices
+end_header
+".to_vec();
+        for (position, color) in [([0.0f32, 0.0, 0.0], [65535u16, 0, 0, 65535]), ([2.0, 0.0, 0.0], [0, 65535, 0, 0]), ([0.0, -3.0, 0.5], [0, 0, 13107, 65535])] {
+            bytes.extend(position.iter().flat_map(|value| value.to_be_bytes()));
+            bytes.extend(color.iter().flat_map(|value| value.to_be_bytes()));
+        }
+        bytes.extend_from_slice(&[7, 3]);
+        bytes.extend([0u32, 2, 1].iter().flat_map(|index| index.to_be_bytes()));
+
+        let mesh = parse(&bytes, "triangle".to_string()).unwrap();
+        assert_eq!(mesh.positions, vec![glm::vec3(0.0, 0.0, 0.0), glm::vec3(2.0, 0.0, 0.0), glm::vec3(0.0, -3.0, 0.5)]);
+        // The list is found after the scalar before it
+        assert_eq!(mesh.indices, vec![0, 2, 1]);
+        // ushort colors are scaled by 65535
+        assert_colors_near(&mesh.colors, &[
+            glm::vec4(1.0, 0.0, 0.0, 1.0),
+            glm::vec4(0.0, 1.0, 0.0, 0.0),
+            glm::vec4(0.0, 0.0, 0.2, 1.0),
+        ]);
+    }
+
+    #[test]
+    fn malformed_faces_are_errors() {
+        let scalar_indices = ASCII.replace("property list uchar int vertex_indices", "property int vertex_indices")
+            .replace("4 0 1 2 3", "0");
+        let error = parse(scalar_indices.as_bytes(), String::new()).unwrap_err();
+        assert!(error.contains("not a list"), "{}", error);
+
+        let out_of_range = ASCII.replace("4 0 1 2 3", "3 0 1 4");
+        let error = parse(out_of_range.as_bytes(), String::new()).unwrap_err();
+        assert!(error.contains("vertex 4 of 4"), "{}", error);
+
+        let truncated = &ASCII[..ASCII.len() - 4];
+        assert!(parse(truncated.as_bytes(), String::new()).is_err());
+    }
+
+    #[test]
+    fn round_trips_every_attribute() {
+        let mesh = pyramid();
+        let mut bytes = vec![];
+        write(&mut bytes, &mesh).unwrap();
+        let loaded = parse(&bytes, mesh.name.clone()).unwrap();
+
+        assert_eq!(loaded.positions, mesh.positions);
+        assert_eq!(loaded.normals, mesh.normals);
+        assert_eq!(loaded.uvs, mesh.uvs);
+        assert_eq!(loaded.indices, mesh.indices);
+        // Colors are stored as bytes
+        for (loaded, color) in loaded.colors.iter().zip(&mesh.colors) {
+            assert!(glm::comp_max(&(loaded - color).abs()) < 1.0 / 255.0);
+        }
+        assert_eq!(loaded.colors.len(), mesh.colors.len());
+    }
+
+    #[test]
+    fn round_trips_positions_only_and_computes_normals() {
+        let mesh = MeshData { normals: vec![], uvs: vec![], colors: vec![], ..pyramid() };
+        let path = std::env::temp_dir().join(format!("gloom-rs-positions-{}.ply", std::process::id()));
+        model::save(&path, &Model { meshes: vec![mesh.clone()], ..Model::default() }).unwrap();
+        let loaded = model::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let loaded = &loaded.unwrap().meshes[0];
+
+        assert_eq!(loaded.name, format!("gloom-rs-positions-{}", std::process::id()));
+        assert!(loaded.uvs.is_empty() && loaded.colors.is_empty());
+        // Flat normals split every corner into its own vertex
+        assert_eq!(loaded.triangle_count(), mesh.triangle_count());
+        assert_eq!(loaded.vertex_count(), 12);
+        for (triangle, loaded_triangle) in mesh.triangles().zip(loaded.triangles()) {
+            let original = triangle.map(|index| mesh.positions[index as usize]);
+            assert_eq!(loaded_triangle.map(|index| loaded.positions[index as usize]), original);
+        }
+    }
+
+    #[test]
+    fn saving_several_meshes_merges_them() {
+        let mut moved = pyramid();
+        moved.positions.iter_mut().for_each(|position| position.x += 2.0);
+        let model = Model { meshes: vec![pyramid(), moved.clone()], ..Model::default() };
+        let path = std::env::temp_dir().join(format!("gloom-rs-merged-{}.ply", std::process::id()));
+        model::save(&path, &model).unwrap();
+        let loaded = model::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let loaded = &loaded.unwrap().meshes[0];
+
+        assert_eq!(loaded.vertex_count(), 8);
+        assert_eq!(loaded.positions[4..], moved.positions[..]);
+        assert_eq!(loaded.indices[12..], moved.indices.iter().map(|index| index + 4).collect::<Vec<_>>()[..]);
+    }
+}
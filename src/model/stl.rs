@@ -0,0 +1,245 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+};
+
+use crate::geometry::{bits, normals, MeshData};
+
+use super::{Model, ModelError};
+
+/// One triangle, with the normal stored next to it in the file.
+struct Facet {
+    normal: glm::Vec3,
+    vertices: [glm::Vec3; 3],
+}
+
+const BINARY_HEADER_SIZE: usize = 80;
+const BINARY_FACET_SIZE: usize = 50;
+
+/// Whether `bytes` is exactly the size of a binary STL with the facet count in its
+/// header. ASCII files start with `solid`, but so do many binary ones.
+fn is_binary(bytes: &[u8]) -> bool {
+    bytes.len() >= BINARY_HEADER_SIZE + 4 && {
+        let count = &bytes[BINARY_HEADER_SIZE..BINARY_HEADER_SIZE + 4];
+        let count = u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as usize;
+        bytes.len() == BINARY_HEADER_SIZE + 4 + count * BINARY_FACET_SIZE
+    }
+}
+
+fn parse_binary(bytes: &[u8]) -> Vec<Facet> {
+    let vector = |bytes: &[u8]| {
+        let component = |index: usize| f32::from_le_bytes([bytes[index], bytes[index + 1], bytes[index + 2], bytes[index + 3]]);
+        glm::vec3(component(0), component(4), component(8))
+    };
+    bytes[BINARY_HEADER_SIZE + 4..].chunks_exact(BINARY_FACET_SIZE)
+        .map(|facet| Facet {
+            normal: vector(&facet[0..12]),
+            vertices: [vector(&facet[12..24]), vector(&facet[24..36]), vector(&facet[36..48])],
+        })
+        .collect()
+}
+
+/// Every solid in the file, with its name.
+fn parse_ascii(text: &str) -> Result<Vec<(String, Vec<Facet>)>, String> {
+    let mut solids: Vec<(String, Vec<Facet>)> = vec![];
+    // The normal and vertices of the facet being read
+    let mut facet: Option<(glm::Vec3, Vec<glm::Vec3>)> = None;
+    for line in text.lines() {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let number = |word: &str| word.parse::<f32>().map_err(|_| format!("bad number \"{}\"", word));
+        match words.as_slice() {
+            ["solid", name @ ..] => {
+                solids.push((name.join(" "), vec![]));
+            },
+            ["facet", "normal", x, y, z] => {
+                facet = Some((glm::vec3(number(x)?, number(y)?, number(z)?), vec![]));
+            },
+            ["vertex", x, y, z] => {
+                let (_, vertices) = facet.as_mut().ok_or("vertex outside of a facet")?;
+                vertices.push(glm::vec3(number(x)?, number(y)?, number(z)?));
+            },
+            ["endfacet"] => {
+                let (normal, vertices) = facet.take().ok_or("endfacet without a facet")?;
+                let &[a, b, c] = vertices.as_slice() else {
+                    return Err(format!("facet with {} vertices", vertices.len()));
+                };
+                let (_, facets) = solids.last_mut().ok_or("facet outside of a solid")?;
+                facets.push(Facet { normal, vertices: [a, b, c] });
+            },
+            ["outer", "loop"] | ["endloop"] | ["endsolid", ..] | [] => {},
+            _ => { return Err(format!("unexpected line \"{}\"", line.trim())) },
+        }
+    }
+    Ok(solids)
+}
+
+/// An indexed mesh where corners with the same position and normal share a vertex.
+/// Normals that are missing (zero) or not normalized are recomputed from the winding.
+fn mesh_data(name: String, facets: &[Facet]) -> MeshData {
+    let mut mesh = MeshData { name, ..MeshData::default() };
+    let mut vertices: HashMap<([u32; 3], [u32; 3]), u32> = HashMap::new();
+    for facet in facets {
+        let [a, b, c] = &facet.vertices;
+        let length = facet.normal.norm();
+        let normal = if (length - 1.0).abs() < 1e-3 { facet.normal / length } else { normals::face_normal(a, b, c) };
+        for position in &facet.vertices {
+            let index = *vertices.entry((bits(position), bits(&normal))).or_insert_with(|| {
+                mesh.positions.push(*position);
+                mesh.normals.push(normal);
+                (mesh.positions.len() - 1) as u32
+            });
+            mesh.indices.push(index);
+        }
+    }
+    mesh
+}
+
+/// Load an ASCII or binary STL file, with one mesh per solid.
+///
+/// STL stores every triangle separately, so corners are merged into shared vertices
+/// wherever both the position and the facet normal are the same. That keeps the
+/// facets flat-shaded while sharing vertices across flat regions.
+pub fn load(path: &Path) -> Result<Model, ModelError> {
+    let bytes = fs::read(path).map_err(|source| ModelError::Io { path: path.to_path_buf(), source })?;
+    let solids = if is_binary(&bytes) {
+        let name = path.file_stem().map_or(String::new(), |stem| stem.to_string_lossy().into_owned());
+        vec![(name, parse_binary(&bytes))]
+    } else if bytes.starts_with(b"solid") {
+        let text = String::from_utf8_lossy(&bytes);
+        parse_ascii(&text).map_err(|message| ModelError::Parse { path: path.to_path_buf(), message })?
+    } else {
+        return Err(ModelError::Parse {
+            path: path.to_path_buf(),
+            message: "neither an ASCII STL nor a binary STL of the size its header says".to_string(),
+        });
+    };
+
+    Ok(Model {
+        meshes: solids.into_iter().map(|(name, facets)| mesh_data(name, &facets)).collect(),
+        ..Model::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A unit quad facing +z, then a wall facing -y along its bottom edge whose normal
+    /// is left out, in a second solid.
+    const ASCII: &str = "solid quad
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 1 1 0
+    endloop
+  endfacet
+  facet normal -0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 1 0
+      vertex 0 1 0
+    endloop
+  endfacet
+endsolid quad
+solid the wall
+  facet normal 0 0 0
+    outer loop
+      vertex 1 0 0
+      vertex 0 0 0
+      vertex 0 0 -1
+    endloop
+  endfacet
+endsolid the wall
+";
+
+    fn ascii_facets() -> Vec<Facet> {
+        parse_ascii(ASCII).unwrap().into_iter().flat_map(|(_, facets)| facets).collect()
+    }
+
+    fn binary(header: &[u8], facets: &[[[f32; 3]; 4]]) -> Vec<u8> {
+        let mut bytes = header.to_vec();
+        bytes.resize(BINARY_HEADER_SIZE, 0);
+        bytes.extend_from_slice(&(facets.len() as u32).to_le_bytes());
+        for facet in facets {
+            bytes.extend(facet.iter().flatten().flat_map(|value| value.to_le_bytes()));
+            bytes.extend_from_slice(&[0, 0]);
+        }
+        bytes
+    }
+
+    #[test]
+    fn parses_ascii_solids() {
+        let solids = parse_ascii(ASCII).unwrap();
+        assert_eq!(solids.len(), 2);
+        assert_eq!(solids[0].0, "quad");
+        assert_eq!(solids[0].1.len(), 2);
+        assert_eq!(solids[1].0, "the wall");
+        assert_eq!(solids[1].1[0].vertices[2], glm::vec3(0.0, 0.0, -1.0));
+
+        assert!(parse_ascii("solid broken\n  facet normal 0 0 1\n    vertex 0 0 zero\n").is_err());
+        assert!(parse_ascii("solid short\n  facet normal 0 0 1\n    vertex 0 0 0\n  endfacet\n").is_err());
+    }
+
+    #[test]
+    fn merges_corners_with_the_same_position_and_normal() {
+        let mesh = mesh_data("merged".to_string(), &ascii_facets());
+
+        // The quad's halves share their diagonal, -0 and 0 included, but not the wall's edge
+        assert_eq!(mesh.vertex_count(), 7);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3, 4, 5, 6]);
+        assert_eq!(mesh.positions[4], mesh.positions[1]);
+        assert_eq!(mesh.normals[..4], [glm::vec3(0.0, 0.0, 1.0); 4]);
+        // The missing normal is computed from the winding
+        assert_eq!(mesh.normals[4..], [glm::vec3(0.0, -1.0, 0.0); 3]);
+
+        // Unnormalized normals are recomputed too
+        let mut facets = ascii_facets();
+        facets[0].normal = glm::vec3(0.0, 0.0, 2.0);
+        assert_eq!(mesh_data("merged".to_string(), &facets), mesh);
+    }
+
+    #[test]
+    fn detects_binary_files_starting_with_solid() {
+        let quad = [
+            [[0.0, 0.0, 1.0], [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0]],
+            [[0.0, 0.0, 1.0], [0.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]],
+        ];
+        let bytes = binary(b"solid exported by a tool that writes binary", &quad);
+        assert!(is_binary(&bytes));
+        assert!(!is_binary(&bytes[..bytes.len() - 1]));
+        assert!(!is_binary(ASCII.as_bytes()));
+
+        let facets = parse_binary(&bytes);
+        assert_eq!(facets.len(), 2);
+        assert_eq!(facets[1].normal, glm::vec3(0.0, 0.0, 1.0));
+        assert_eq!(facets[1].vertices, [glm::vec3(0.0, 0.0, 0.0), glm::vec3(1.0, 1.0, 0.0), glm::vec3(0.0, 1.0, 0.0)]);
+    }
+
+    #[test]
+    fn loads_either_encoding() {
+        let path = std::env::temp_dir().join(format!("gloom-rs-stl-{}.stl", std::process::id()));
+        let quad = [
+            [[0.0, 0.0, 1.0], [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0]],
+            [[0.0, 0.0, 1.0], [0.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]],
+        ];
+        let mut results = vec![];
+        for bytes in [binary(b"solid", &quad), ASCII.as_bytes().to_vec(), b"not an STL".to_vec()] {
+            std::fs::write(&path, bytes).unwrap();
+            results.push(load(&path));
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        let binary = results.remove(0).unwrap();
+        assert_eq!(binary.meshes.len(), 1);
+        assert_eq!(binary.meshes[0].name, format!("gloom-rs-stl-{}", std::process::id()));
+        assert_eq!(binary.meshes[0].vertex_count(), 4);
+
+        let ascii = results.remove(0).unwrap();
+        assert_eq!(ascii.meshes.iter().map(|mesh| mesh.name.as_str()).collect::<Vec<_>>(), ["quad", "the wall"]);
+        assert_eq!(ascii.meshes[0].vertex_count(), 4);
+
+        assert!(matches!(results.remove(0), Err(ModelError::Parse { .. })));
+    }
+}
@@ -1,156 +1,602 @@
-use gl;
 use std::{
     ptr,
     str,
+    fmt,
+    any::TypeId,
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
     ffi::CString,
-    path::Path,
+    path::{Path, PathBuf},
 };
-use libc::c_char;
+
+mod cache;
+mod compute;
+mod diagnostics;
+mod embedded;
+mod feedback;
+mod pipeline;
+mod preprocess;
+mod reflection;
+mod reload;
+mod uniform;
+mod variants;
+mod vertex_input;
+
+pub use compute::{ComputeShader, DEFAULT_BARRIERS};
+pub use diagnostics::{Diagnostic, Severity};
+pub use feedback::{FeedbackCapture, FeedbackPrimitive};
+pub use pipeline::Pipeline;
+pub use reflection::{type_components, type_name, Attribute, Block, BlockMember, FeedbackBuffer, FeedbackVarying, ProgramInterface, Uniform};
+pub use reload::ReloadableShader;
+pub use uniform::{UniformElement, UniformValue};
+pub use variants::{Defines, ShaderVariants};
+pub use vertex_input::{AttributeKind, AttributeMismatch, VertexArrayAttribute};
+
+use crate::gl_object::{Program, ShaderObject};
+use crate::layout::{self, BlockLayout, LayoutError, LayoutMismatch, LayoutRule};
+use crate::storage_buffer::StorageBuffer;
+use crate::uniform_buffer::UniformBuffer;
 
 pub struct Shader {
-    pub program_id: u32,
+    program: Program,
+    interface: ProgramInterface,
+    uniform_locations: HashMap<String, i32>,
+    // Names we already warned about, so a missing uniform doesn't flood the log every frame
+    missing_uniforms: RefCell<HashSet<String>>,
+    // Vertex array objects already compared against this program's attributes
+    checked_vertex_arrays: RefCell<HashSet<u32>>,
+    // Blocks already checked against the Rust type bound to them
+    checked_blocks: RefCell<HashSet<(String, TypeId)>>,
+    sources: Vec<ShaderSource>,
+    options: BuildOptions,
+    dependencies: Vec<PathBuf>,
 }
 
 pub struct ShaderBuilder {
-    program_id: u32,
-    shaders: Vec::<u32>,
+    program: Program,
+    stages: Vec<Stage>,
+    sources: Vec<ShaderSource>,
+    options: BuildOptions,
+    dependencies: Vec<PathBuf>,
+}
+
+/// Settings that apply to every stage of a builder, kept so the program can be rebuilt the same way.
+#[derive(Debug, Clone, Default)]
+struct BuildOptions {
+    include_dirs: Vec<PathBuf>,
+    cache_dir: Option<PathBuf>,
+    defines: BTreeMap<String, String>,
+    separable: bool,
+    prefer_disk: bool,
+    /// Varyings to capture and `gl::INTERLEAVED_ATTRIBS` or `gl::SEPARATE_ATTRIBS`.
+    feedback_varyings: Option<(Vec<String>, gl::types::GLenum)>,
+}
+
+/// A preprocessed stage waiting to be compiled by `link`.
+struct Stage {
+    shader_type: ShaderType,
+    path: Option<PathBuf>,
+    preprocessed: preprocess::Preprocessed,
+}
+
+/// Where a stage came from, kept around so the program can be rebuilt later.
+#[derive(Debug, Clone)]
+enum ShaderSource {
+    File(PathBuf),
+    Inline(ShaderType, String),
 }
 
 #[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ShaderType {
     Vertex,
     Fragment,
     TessellationControl,
     TessellationEvaluation,
     Geometry,
+    Compute,
+}
+
+/// Everything that can go wrong while building a shader program.
+#[derive(Debug)]
+pub enum ShaderError {
+    /// The shader source could not be read from disk.
+    Io { path: PathBuf, source: std::io::Error },
+    /// The file extension does not map to a known shader stage.
+    UnknownExtension(PathBuf),
+    /// A stage failed to compile. `path` is `None` for sources passed in directly.
+    /// `log` is the driver's full info log, `diagnostics` the same log split into
+    /// entries and mapped back through `#include`s to the files they came from.
+    Compile { stage: ShaderType, path: Option<PathBuf>, log: String, diagnostics: Vec<Diagnostic> },
+    /// The program failed to link.
+    Link { log: String },
+    /// An `#include` could not be found next to the including file or in any include directory.
+    Include { path: Option<PathBuf>, line: usize, target: String },
+    /// A file ended up including itself. The chain starts at the outermost file.
+    IncludeCycle(Vec<PathBuf>),
+    /// The builder has no stage of the type needed for what it is being linked into.
+    MissingStage(ShaderType),
+    /// A program was added to a `Pipeline` without being built with `ShaderBuilder::separable`.
+    NotSeparable,
+    /// The stages of a `Pipeline` don't work together, e.g. because their interfaces don't match.
+    Pipeline { log: String },
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderError::Io { path, source } => {
+                write!(f, "Failed to read shader source {}: {}", path.display(), source)
+            },
+            ShaderError::UnknownExtension(path) => {
+                write!(f, "Unknown shader extension for file with path: {}", path.display())
+            },
+            ShaderError::Compile { stage, path, log, diagnostics } => {
+                match path {
+                    Some(path) => write!(f, "{:?} shader {} failed to compile:", stage, path.display())?,
+                    None => write!(f, "{:?} shader failed to compile:", stage)?,
+                }
+                if diagnostics.is_empty() {
+                    return write!(f, "\n{}", log);
+                }
+                for diagnostic in diagnostics {
+                    write!(f, "\n{}", diagnostic)?;
+                }
+                Ok(())
+            },
+            ShaderError::Link { log } => {
+                write!(f, "Shader program failed to link:\n{}", log)
+            },
+            ShaderError::Include { path, line, target } => {
+                let path = path.as_ref().map_or("<inline>".into(), |path| path.display().to_string());
+                write!(f, "{}:{}: could not find included file \"{}\"", path, line, target)
+            },
+            ShaderError::IncludeCycle(chain) => {
+                let chain: Vec<_> = chain.iter().map(|path| path.display().to_string()).collect();
+                write!(f, "Include cycle: {}", chain.join(" -> "))
+            },
+            ShaderError::MissingStage(stage) => {
+                write!(f, "Shader program has no {:?} stage", stage)
+            },
+            ShaderError::NotSeparable => {
+                write!(f, "Only separable programs can be used in a program pipeline")
+            },
+            ShaderError::Pipeline { log } => {
+                write!(f, "Program pipeline failed to validate:\n{}", log)
+            },
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ShaderError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
 }
 
 impl Shader {
     // Make sure the shader is active before calling this
     pub unsafe fn get_uniform_location(&self, name: &str) -> i32 {
         let name_cstr = CString::new(name).expect("CString::new failed");
-        gl::GetUniformLocation(self.program_id, name_cstr.as_ptr())
+        gl::GetUniformLocation(self.program_id(), name_cstr.as_ptr())
+    }
+
+    pub fn program_id(&self) -> u32 {
+        self.program.id()
     }
 
     pub unsafe fn activate(&self) {
-        gl::UseProgram(self.program_id);
+        gl::UseProgram(self.program_id());
+    }
+
+    /// Upload `value` to the uniform called `name`. The program does not need to be active.
+    ///
+    /// Prints a warning, once per name, if the program has no active uniform by that
+    /// name. This is also what happens when the compiler optimized an unused uniform away.
+    pub unsafe fn set_uniform<T: UniformValue + ?Sized>(&self, name: &str, value: &T) {
+        match self.uniform_locations.get(name) {
+            Some(&location) => value.upload(self.program_id(), location),
+            None => {
+                if self.missing_uniforms.borrow_mut().insert(name.to_string()) {
+                    println!("WARNING: program {} has no active uniform named \"{}\"", self.program_id(), name);
+                }
+            },
+        }
+    }
+
+    /// The active attributes, uniforms and blocks of this program, as reported at link time.
+    pub fn interface(&self) -> &ProgramInterface {
+        &self.interface
+    }
+
+    /// Compare the enabled attribute arrays of `vao` against this program's active attributes.
+    pub unsafe fn validate_vertex_array(&self, vao: u32) -> Result<(), Vec<AttributeMismatch>> {
+        let vao_attributes = vertex_input::vertex_array_attributes(vao);
+        let mismatches = vertex_input::compare(&vao_attributes, &self.interface);
+        if mismatches.is_empty() { Ok(()) } else { Err(mismatches) }
+    }
+
+    /// Validate `vao` the first time it is drawn with this program, printing any mismatches.
+    /// `Mesh::draw_with` calls this; later calls with the same VAO do nothing.
+    pub unsafe fn check_vertex_array_once(&self, vao: u32) {
+        if !self.checked_vertex_arrays.borrow_mut().insert(vao) {
+            return;
+        }
+        if let Err(mismatches) = self.validate_vertex_array(vao) {
+            println!("WARNING: VAO {} does not match the attributes of program {}:", vao, self.program_id());
+            for mismatch in mismatches {
+                println!("  {}", mismatch);
+            }
+        }
+    }
+
+    /// Bind `buffer` to the binding point of the uniform block called `block`.
+    ///
+    /// The first time a given `T` is bound to a block, its std140 layout is checked
+    /// against the offsets the program reports. Give each block an explicit
+    /// `layout(binding = N)`, as blocks without one all share binding point 0.
+    pub unsafe fn bind_uniform_buffer<T: BlockLayout + 'static>(&self, block: &str, buffer: &UniformBuffer<T>) -> Result<(), LayoutError> {
+        let reflected = self.interface.uniform_block(block).ok_or_else(|| LayoutError {
+            block: block.to_string(),
+            mismatches: vec![LayoutMismatch::MissingBlock],
+        })?;
+        if self.checked_blocks.borrow_mut().insert((block.to_string(), TypeId::of::<T>())) {
+            layout::check_block::<T>(LayoutRule::Std140, reflected)?;
+        }
+        buffer.bind(reflected.binding);
+        Ok(())
+    }
+
+    /// Bind `buffer` to the binding point of the shader storage block called `block`.
+    ///
+    /// The block must hold a single runtime-sized array of `T`. The first time a given
+    /// `T` is bound to a block, its std430 layout is checked against the program.
+    pub unsafe fn bind_storage_buffer<T: BlockLayout + 'static>(&self, block: &str, buffer: &StorageBuffer<T>) -> Result<(), LayoutError> {
+        let reflected = self.interface.storage_block(block).ok_or_else(|| LayoutError {
+            block: block.to_string(),
+            mismatches: vec![LayoutMismatch::MissingBlock],
+        })?;
+        if self.checked_blocks.borrow_mut().insert((block.to_string(), TypeId::of::<T>())) {
+            layout::check_runtime_array::<T>(LayoutRule::Std430, reflected)?;
+        }
+        buffer.bind(reflected.binding);
+        Ok(())
+    }
+
+    /// The files this program was built from, in the order they were attached.
+    pub fn source_paths(&self) -> impl Iterator<Item = &Path> {
+        self.sources.iter().filter_map(|source| match source {
+            ShaderSource::File(path) => Some(path.as_path()),
+            ShaderSource::Inline(..) => None,
+        })
+    }
+
+    /// The stages linked into this program, in the order they were attached.
+    pub fn stages(&self) -> impl Iterator<Item = ShaderType> + '_ {
+        self.sources.iter().map(|source| match source {
+            ShaderSource::File(path) => path.extension().and_then(ShaderType::from_ext).unwrap(),
+            ShaderSource::Inline(shader_type, _) => *shader_type,
+        })
+    }
+
+    /// Whether this program was built with `ShaderBuilder::separable`.
+    pub fn is_separable(&self) -> bool {
+        self.options.separable
+    }
+
+    /// Every file read while building this program, including `#include`d files.
+    pub fn dependencies(&self) -> &[PathBuf] {
+        &self.dependencies
+    }
+
+    /// Compile and link a fresh program from the same sources as this one.
+    unsafe fn rebuild(&self) -> Result<Shader, ShaderError> {
+        let mut builder = ShaderBuilder::with_options(self.options.clone());
+        for source in &self.sources {
+            builder = match source {
+                ShaderSource::File(path) => builder.attach_path(path)?,
+                ShaderSource::Inline(shader_type, src) => builder.compile_shader(src, *shader_type)?,
+            };
+        }
+        builder.link()
     }
 }
 
-impl Into<gl::types::GLenum> for ShaderType {
-    fn into(self) -> gl::types::GLenum {
-        match self {
+impl From<ShaderType> for gl::types::GLenum {
+    fn from(shader_type: ShaderType) -> Self {
+        match shader_type {
             ShaderType::Vertex                  => { gl::VERTEX_SHADER          },
             ShaderType::Fragment                => { gl::FRAGMENT_SHADER        },
             ShaderType::TessellationControl     => { gl::TESS_CONTROL_SHADER    },
             ShaderType::TessellationEvaluation  => { gl::TESS_EVALUATION_SHADER } ,
             ShaderType::Geometry                => { gl::GEOMETRY_SHADER        },
+            ShaderType::Compute                 => { gl::COMPUTE_SHADER         },
         }
     }
 }
 
 impl ShaderType {
-    fn from_ext(ext: &std::ffi::OsStr) -> Result<ShaderType, String> {
-        match ext.to_str().expect("Failed to read extension") {
-            "vert" => { Ok(ShaderType::Vertex) },
-            "frag" => { Ok(ShaderType::Fragment) },
-            "tcs"  => { Ok(ShaderType::TessellationControl) },
-            "tes"  => { Ok(ShaderType::TessellationEvaluation) },
-            "geom" => { Ok(ShaderType::Geometry) },
-            e => { Err(e.to_string()) },
+    /// The bit selecting this stage in `glUseProgramStages`.
+    fn stage_bit(self) -> gl::types::GLbitfield {
+        match self {
+            ShaderType::Vertex                  => { gl::VERTEX_SHADER_BIT          },
+            ShaderType::Fragment                => { gl::FRAGMENT_SHADER_BIT        },
+            ShaderType::TessellationControl     => { gl::TESS_CONTROL_SHADER_BIT    },
+            ShaderType::TessellationEvaluation  => { gl::TESS_EVALUATION_SHADER_BIT },
+            ShaderType::Geometry                => { gl::GEOMETRY_SHADER_BIT        },
+            ShaderType::Compute                 => { gl::COMPUTE_SHADER_BIT         },
+        }
+    }
+
+    fn from_ext(ext: &std::ffi::OsStr) -> Option<ShaderType> {
+        match ext.to_str()? {
+            "vert" => { Some(ShaderType::Vertex) },
+            "frag" => { Some(ShaderType::Fragment) },
+            "tcs"  => { Some(ShaderType::TessellationControl) },
+            "tes"  => { Some(ShaderType::TessellationEvaluation) },
+            "geom" => { Some(ShaderType::Geometry) },
+            "comp" => { Some(ShaderType::Compute) },
+            _ => { None },
         }
     }
 }
 
 impl ShaderBuilder {
     pub unsafe fn new() -> ShaderBuilder {
+        ShaderBuilder::with_options(BuildOptions::default())
+    }
+
+    unsafe fn with_options(options: BuildOptions) -> ShaderBuilder {
         ShaderBuilder {
-            program_id: gl::CreateProgram(),
-            shaders: vec![],
+            program: Program::new(),
+            stages: vec![],
+            sources: vec![],
+            options,
+            dependencies: vec![],
         }
     }
 
-    pub unsafe fn attach_file(self, shader_path: &str) -> ShaderBuilder {
-        let path = Path::new(shader_path);
-        if let Some(extension) = path.extension() {
-            let shader_type = ShaderType::from_ext(extension)
-                .expect("Failed to parse file extension.");
-            let shader_src = std::fs::read_to_string(path)
-                .expect(&format!("Failed to read shader source. {}", shader_path));
-            let builder = self.compile_shader(&shader_src, shader_type);
-            return builder;
-        } else {
-            panic!("Failed to read extension of file with path: {}", shader_path);
+    /// Add a directory to search for `#include`d files that aren't found next to the
+    /// including file. Directories are searched in the order they were added.
+    /// Only affects stages attached after this call.
+    pub fn include_dir<P: AsRef<Path>>(mut self, dir: P) -> ShaderBuilder {
+        self.options.include_dirs.push(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Store linked program binaries in `dir` and load them on later runs instead of
+    /// compiling. Binaries are keyed by the final source of every stage and the GL
+    /// vendor, renderer and version, and anything the driver rejects is recompiled.
+    pub fn cache_dir<P: AsRef<Path>>(mut self, dir: P) -> ShaderBuilder {
+        self.options.cache_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Read files from disk rather than from the copies of `shaders/` compiled into the
+    /// binary, falling back to those only if a file is missing. Needed for hot reloading,
+    /// since the embedded copies never change. Only affects stages attached after this call.
+    pub fn prefer_disk(mut self, prefer_disk: bool) -> ShaderBuilder {
+        self.options.prefer_disk = prefer_disk;
+        self
+    }
+
+    /// Link a separable program, which can be combined with programs providing the other
+    /// stages in a `Pipeline` instead of being used on its own. Usually it holds a single
+    /// stage, so one vertex shader can be paired with many fragment shaders without
+    /// linking every combination.
+    pub fn separable(mut self) -> ShaderBuilder {
+        self.options.separable = true;
+        self
+    }
+
+    /// Capture the vertex outputs called `varyings` with transform feedback, see
+    /// `FeedbackCapture`. With `interleaved`, every vertex is written to one buffer in the
+    /// order given here, which may include `gl_SkipComponents1` to `4` for padding.
+    /// Otherwise each varying gets a buffer of its own.
+    pub fn feedback_varyings<'a, I: IntoIterator<Item = &'a str>>(mut self, varyings: I, interleaved: bool) -> ShaderBuilder {
+        let varyings = varyings.into_iter().map(str::to_string).collect();
+        let mode = if interleaved { gl::INTERLEAVED_ATTRIBS } else { gl::SEPARATE_ATTRIBS };
+        self.options.feedback_varyings = Some((varyings, mode));
+        self
+    }
+
+    /// `#define name value` in every stage, right after the `#version` line. Applies to
+    /// all stages, including ones attached before this call. An empty `value` defines
+    /// a plain flag for use with `#ifdef`.
+    pub fn define(mut self, name: &str, value: &str) -> ShaderBuilder {
+        self.options.defines.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// `define` every entry of `defines`.
+    pub fn defines<'a, I: IntoIterator<Item = (&'a str, &'a str)>>(self, defines: I) -> ShaderBuilder {
+        defines.into_iter().fold(self, |builder, (name, value)| builder.define(name, value))
+    }
+
+    /// Add a stage from a file, with the stage type taken from the extension. Files under
+    /// `shaders/` are compiled into the binary and read from there, see `prefer_disk`.
+    pub unsafe fn attach_file(self, shader_path: &str) -> Result<ShaderBuilder, ShaderError> {
+        self.attach_path(Path::new(shader_path))
+    }
+
+    unsafe fn attach_path(mut self, path: &Path) -> Result<ShaderBuilder, ShaderError> {
+        let shader_type = path.extension()
+            .and_then(ShaderType::from_ext)
+            .ok_or_else(|| ShaderError::UnknownExtension(path.to_path_buf()))?;
+        let shader_src = embedded::read(path, self.options.prefer_disk)
+            .map_err(|source| ShaderError::Io { path: path.to_path_buf(), source })?;
+        self.sources.push(ShaderSource::File(path.to_path_buf()));
+        self.add_stage(&shader_src, shader_type, Some(path))
+    }
+
+    /// Add a stage from source in memory. Like `attach_file`, the stage is only
+    /// preprocessed here; compile errors are reported by `link`.
+    pub unsafe fn compile_shader(mut self, shader_src: &str, shader_type: ShaderType) -> Result<ShaderBuilder, ShaderError> {
+        self.sources.push(ShaderSource::Inline(shader_type, shader_src.to_string()));
+        self.add_stage(shader_src, shader_type, None)
+    }
+
+    fn add_stage(mut self, shader_src: &str, shader_type: ShaderType, path: Option<&Path>) -> Result<ShaderBuilder, ShaderError> {
+        let preprocessed = preprocess::preprocess(shader_src, path, &self.options.include_dirs, self.options.prefer_disk)?;
+        for file in preprocessed.files() {
+            if !self.dependencies.iter().any(|dependency| dependency == file) {
+                self.dependencies.push(file.to_path_buf());
+            }
         }
+        self.stages.push(Stage {
+            shader_type,
+            path: path.map(Path::to_path_buf),
+            preprocessed,
+        });
+        Ok(self)
     }
 
-    pub unsafe fn compile_shader(mut self, shader_src: &str, shader_type: ShaderType) -> ShaderBuilder {
-        let shader = gl::CreateShader(shader_type.into());
-        let c_str_shader = CString::new(shader_src.as_bytes()).unwrap();
-        gl::ShaderSource(shader, 1, &c_str_shader.as_ptr(), ptr::null());
-        gl::CompileShader(shader);
+    unsafe fn compile_stage(&self, stage: &Stage) -> Result<ShaderObject, ShaderError> {
+        let shader = ShaderObject::new(stage.shader_type.into());
+        let c_str_shader = CString::new(stage.preprocessed.source.as_bytes()).unwrap();
+        gl::ShaderSource(shader.id(), 1, &c_str_shader.as_ptr(), ptr::null());
+        gl::CompileShader(shader.id());
 
-        if !self.check_shader_errors(shader) {
-            panic!("Shader failed to compile.");
+        if let Err(log) = self.check_shader_errors(shader.id()) {
+            return Err(ShaderError::Compile {
+                stage: stage.shader_type,
+                path: stage.path.clone(),
+                diagnostics: diagnostics::parse_log(&log, &stage.preprocessed),
+                log,
+            });
         }
 
-        self.shaders.push(shader);
+        Ok(shader)
+    }
 
-        self
+    /// Compile every stage, then link them into the program.
+    unsafe fn compile_and_link(&self) -> Result<(), ShaderError> {
+        let shaders = self.stages.iter()
+            .map(|stage| self.compile_stage(stage))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for shader in &shaders {
+            gl::AttachShader(self.program.id(), shader.id());
+        }
+        gl::LinkProgram(self.program.id());
+
+        let link_result = self.check_linker_errors();
+
+        // The shader objects are deleted when dropped, but only once they are detached
+        for shader in &shaders {
+            gl::DetachShader(self.program.id(), shader.id());
+        }
+
+        link_result.map_err(|log| ShaderError::Link { log })
     }
 
-    unsafe fn check_shader_errors(&self, shader_id: u32) -> bool {
+    unsafe fn check_shader_errors(&self, shader_id: u32) -> Result<(), String> {
         let mut success = i32::from(gl::FALSE);
-        let mut info_log = Vec::with_capacity(512);
-        info_log.set_len(512 - 1);
+        let mut log_length = 0;
         gl::GetShaderiv(shader_id, gl::COMPILE_STATUS, &mut success);
         if success != i32::from(gl::TRUE) {
+            gl::GetShaderiv(shader_id, gl::INFO_LOG_LENGTH, &mut log_length);
+            let mut info_log = vec![0u8; log_length.max(1) as usize];
             gl::GetShaderInfoLog(
                 shader_id,
-                512,
-                ptr::null_mut(),
+                info_log.len() as i32,
+                &mut log_length,
                 info_log.as_mut_ptr() as *mut gl::types::GLchar,
             );
-            println!("ERROR::Shader Compilation Failed!\n{}", String::from_utf8_lossy(&info_log));
-            return false;
+            info_log.truncate(log_length as usize);
+            return Err(String::from_utf8_lossy(&info_log).into_owned());
         }
-        true
+        Ok(())
     }
 
-    unsafe fn check_linker_errors(&self) -> bool {
+    unsafe fn check_linker_errors(&self) -> Result<(), String> {
         let mut success = i32::from(gl::FALSE);
-        let mut info_log = Vec::with_capacity(512);
-        info_log.set_len(512 - 1);
-        gl::GetProgramiv(self.program_id, gl::LINK_STATUS, &mut success);
+        let mut log_length = 0;
+        gl::GetProgramiv(self.program.id(), gl::LINK_STATUS, &mut success);
         if success != i32::from(gl::TRUE) {
+            gl::GetProgramiv(self.program.id(), gl::INFO_LOG_LENGTH, &mut log_length);
+            let mut info_log = vec![0u8; log_length.max(1) as usize];
             gl::GetProgramInfoLog(
-                self.program_id,
-                512,
-                ptr::null_mut(),
+                self.program.id(),
+                info_log.len() as i32,
+                &mut log_length,
                 info_log.as_mut_ptr() as *mut gl::types::GLchar,
             );
-            println!("ERROR::SHADER::PROGRAM::COMPILATION_FAILED\n{}", String::from_utf8_lossy(&info_log));
-            return false;
+            info_log.truncate(log_length as usize);
+            return Err(String::from_utf8_lossy(&info_log).into_owned());
         }
-        true
+        Ok(())
     }
 
     #[must_use = "The shader program is useless if not stored in a variable."]
-    pub unsafe fn link(self) -> Shader {
-        for &shader in &self.shaders {
-            gl::AttachShader(self.program_id, shader);
+    pub unsafe fn link(mut self) -> Result<Shader, ShaderError> {
+        for stage in &mut self.stages {
+            stage.preprocessed.inject_defines(&self.options.defines);
         }
-        gl::LinkProgram(self.program_id);
 
-        // todo:: use this to make safer abstraction
-        self.check_linker_errors();
+        let cache = match &self.options.cache_dir {
+            Some(dir) if cache::supported() => {
+                let stages = self.stages.iter()
+                    .map(|stage| (stage.shader_type, stage.preprocessed.source.as_str()));
+                Some((dir.as_path(), cache::key(stages, &self.options)))
+            },
+            _ => None,
+        };
+
+        if self.options.separable {
+            gl::ProgramParameteri(self.program.id(), gl::PROGRAM_SEPARABLE, gl::TRUE as i32);
+        }
+        if let Some((varyings, mode)) = &self.options.feedback_varyings {
+            let varyings: Vec<CString> = varyings.iter()
+                .map(|varying| CString::new(varying.as_str()).expect("CString::new failed"))
+                .collect();
+            let pointers: Vec<_> = varyings.iter().map(|varying| varying.as_ptr()).collect();
+            gl::TransformFeedbackVaryings(self.program.id(), pointers.len() as i32, pointers.as_ptr(), *mode);
+        }
 
-        for &shader in &self.shaders {
-            gl::DeleteShader(shader);
+        match cache {
+            Some((dir, key)) if cache::load(self.program.id(), dir, key) => {},
+            Some((dir, key)) => {
+                gl::ProgramParameteri(self.program.id(), gl::PROGRAM_BINARY_RETRIEVABLE_HINT, gl::TRUE as i32);
+                self.compile_and_link()?;
+                cache::store(self.program.id(), dir, key);
+            },
+            None => self.compile_and_link()?,
         }
 
-        Shader {
-            program_id: self.program_id
+        let interface = ProgramInterface::query(self.program.id());
+        let uniform_locations = interface.uniforms.iter()
+            .flat_map(|uniform| {
+                // Arrays are reported as "name[0]", but are usually set as "name"
+                let base_name = uniform.name.strip_suffix("[0]").map(str::to_string);
+                std::iter::once(uniform.name.clone())
+                    .chain(base_name)
+                    .map(move |name| (name, uniform.location))
+            })
+            .collect();
+
+        Ok(Shader {
+            program: self.program,
+            interface,
+            uniform_locations,
+            missing_uniforms: RefCell::new(HashSet::new()),
+            checked_vertex_arrays: RefCell::new(HashSet::new()),
+            checked_blocks: RefCell::new(HashSet::new()),
+            sources: self.sources,
+            options: self.options,
+            dependencies: self.dependencies,
+        })
+    }
+
+    /// Link a program made of a single compute stage.
+    #[must_use = "The shader program is useless if not stored in a variable."]
+    pub unsafe fn link_compute(self) -> Result<ComputeShader, ShaderError> {
+        if !self.stages.iter().any(|stage| stage.shader_type == ShaderType::Compute) {
+            return Err(ShaderError::MissingStage(ShaderType::Compute));
         }
+        Ok(ComputeShader::new(self.link()?))
     }
 }
@@ -0,0 +1,93 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::util;
+
+use super::{BuildOptions, ShaderType};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Stable across runs and Rust versions, unlike `DefaultHasher`.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        // Length prefix, so ("ab", "c") and ("a", "bc") hash differently
+        for &byte in (bytes.len() as u64).to_le_bytes().iter().chain(bytes) {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+/// A hash of every stage's final source, the options that affect linking, and the driver
+/// that will consume the binary. Any driver update changes the key, since binaries are not
+/// portable between versions.
+pub unsafe fn key<'a>(stages: impl Iterator<Item = (ShaderType, &'a str)>, options: &BuildOptions) -> u64 {
+    let mut hasher = Fnv1a(FNV_OFFSET_BASIS);
+    for name in [gl::VENDOR, gl::RENDERER, gl::VERSION] {
+        hasher.write(util::get_gl_string(name).as_bytes());
+    }
+    hasher.write(&[options.separable as u8]);
+    if let Some((varyings, mode)) = &options.feedback_varyings {
+        hasher.write(&mode.to_le_bytes());
+        for varying in varyings {
+            hasher.write(varying.as_bytes());
+        }
+    }
+    for (shader_type, source) in stages {
+        hasher.write(&gl::types::GLenum::from(shader_type).to_le_bytes());
+        hasher.write(source.as_bytes());
+    }
+    hasher.0
+}
+
+fn cache_path(dir: &Path, key: u64) -> PathBuf {
+    dir.join(format!("{:016x}.bin", key))
+}
+
+/// Whether the driver can save and load program binaries at all.
+pub unsafe fn supported() -> bool {
+    let mut formats = 0;
+    gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut formats);
+    formats > 0
+}
+
+/// Try to initialize `program_id` from a cached binary. Returns `false` if there is no
+/// cached binary or the driver rejected it, in which case the program must be compiled.
+pub unsafe fn load(program_id: u32, dir: &Path, key: u64) -> bool {
+    let bytes = match fs::read(cache_path(dir, key)) {
+        Ok(bytes) if bytes.len() > 4 => bytes,
+        _ => return false,
+    };
+    let (format, binary) = bytes.split_at(4);
+    let format = u32::from_le_bytes([format[0], format[1], format[2], format[3]]);
+    gl::ProgramBinary(program_id, format, binary.as_ptr() as *const _, binary.len() as i32);
+
+    let mut success = i32::from(gl::FALSE);
+    gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut success);
+    success == i32::from(gl::TRUE)
+}
+
+/// Save the binary of a linked program. Failures only print a warning, the cache is an optimization.
+pub unsafe fn store(program_id: u32, dir: &Path, key: u64) {
+    let mut length = 0;
+    gl::GetProgramiv(program_id, gl::PROGRAM_BINARY_LENGTH, &mut length);
+    if length <= 0 {
+        return;
+    }
+    let mut binary = vec![0u8; length as usize];
+    let mut format = 0;
+    gl::GetProgramBinary(program_id, length, &mut length, &mut format, binary.as_mut_ptr() as *mut _);
+    binary.truncate(length as usize);
+
+    let mut bytes = format.to_le_bytes().to_vec();
+    bytes.extend_from_slice(&binary);
+    let path = cache_path(dir, key);
+    if let Err(error) = fs::create_dir_all(dir).and_then(|_| fs::write(&path, bytes)) {
+        println!("WARNING: could not write program binary cache {}: {}", path.display(), error);
+    }
+}
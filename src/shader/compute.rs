@@ -0,0 +1,68 @@
+use gl::types::GLbitfield;
+
+use super::Shader;
+
+/// Barriers issued after `ComputeShader::dispatch`, covering the usual ways the
+/// results are consumed: as vertex or index data, through storage buffers, images
+/// or textures, by indirect draws, or read back with `glGetBufferSubData`.
+pub const DEFAULT_BARRIERS: GLbitfield = gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT
+    | gl::ELEMENT_ARRAY_BARRIER_BIT
+    | gl::SHADER_STORAGE_BARRIER_BIT
+    | gl::SHADER_IMAGE_ACCESS_BARRIER_BIT
+    | gl::TEXTURE_FETCH_BARRIER_BIT
+    | gl::COMMAND_BARRIER_BIT
+    | gl::BUFFER_UPDATE_BARRIER_BIT;
+
+/// A program made of a single compute stage, created with `ShaderBuilder::link_compute`.
+pub struct ComputeShader {
+    shader: Shader,
+    work_group_size: [u32; 3],
+}
+
+impl ComputeShader {
+    pub(super) unsafe fn new(shader: Shader) -> ComputeShader {
+        let mut work_group_size = [0i32; 3];
+        gl::GetProgramiv(shader.program_id(), gl::COMPUTE_WORK_GROUP_SIZE, work_group_size.as_mut_ptr());
+        ComputeShader {
+            shader,
+            work_group_size: [work_group_size[0] as u32, work_group_size[1] as u32, work_group_size[2] as u32],
+        }
+    }
+
+    pub fn shader(&self) -> &Shader {
+        &self.shader
+    }
+
+    /// The `local_size_x/y/z` declared in the shader.
+    pub fn work_group_size(&self) -> [u32; 3] {
+        self.work_group_size
+    }
+
+    /// The number of work groups needed to cover `invocations` threads in each dimension.
+    pub fn work_groups_for(&self, invocations: [u32; 3]) -> [u32; 3] {
+        let [x, y, z] = self.work_group_size;
+        [invocations[0].div_ceil(x), invocations[1].div_ceil(y), invocations[2].div_ceil(z)]
+    }
+
+    /// Run `x * y * z` work groups, then wait on `DEFAULT_BARRIERS` so the results are
+    /// visible to whatever comes next. Leaves this program active.
+    pub unsafe fn dispatch(&self, x: u32, y: u32, z: u32) {
+        self.dispatch_with_barriers(x, y, z, DEFAULT_BARRIERS);
+    }
+
+    /// Like `dispatch`, but only issues `barriers`. Pass `0` to skip the barrier entirely.
+    pub unsafe fn dispatch_with_barriers(&self, x: u32, y: u32, z: u32, barriers: GLbitfield) {
+        self.shader.activate();
+        gl::DispatchCompute(x, y, z);
+        if barriers != 0 {
+            gl::MemoryBarrier(barriers);
+        }
+    }
+
+    /// Dispatch enough work groups to cover `invocations` threads in each dimension.
+    /// The shader must ignore invocations outside the range itself.
+    pub unsafe fn dispatch_invocations(&self, invocations: [u32; 3]) {
+        let [x, y, z] = self.work_groups_for(invocations);
+        self.dispatch(x, y, z);
+    }
+}
@@ -0,0 +1,238 @@
+use std::{
+    fmt,
+    path::PathBuf,
+};
+
+use super::preprocess::Preprocessed;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A single entry from a compiler info log, mapped back to the file it came from.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// `None` for inline sources or when the driver did not report a location.
+    pub path: Option<PathBuf>,
+    /// 1-based line in `path`.
+    pub line: Option<usize>,
+    /// 1-based column, only reported by some drivers.
+    pub column: Option<usize>,
+    pub message: String,
+    /// The offending line of source, for display.
+    pub source_line: Option<String>,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.severity)?;
+        let path = self.path.as_ref().map_or("<inline>".into(), |path| path.display().to_string());
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "{}:{}:{}: ", path, line, column)?,
+            (Some(line), None) => write!(f, "{}:{}: ", path, line)?,
+            (None, _) if self.path.is_some() => write!(f, "{}: ", path)?,
+            (None, _) => {},
+        }
+        write!(f, "{}", self.message)?;
+        if let (Some(line), Some(source_line)) = (self.line, &self.source_line) {
+            let gutter = line.to_string();
+            // Without a column, point at the start of the code on that line
+            let caret = self.column
+                .map(|column| column.saturating_sub(1))
+                .unwrap_or_else(|| source_line.len() - source_line.trim_start().len());
+            let indent: String = source_line.chars()
+                .take(caret)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            write!(f, "\n {} | {}", gutter, source_line)?;
+            write!(f, "\n {} | {}^", " ".repeat(gutter.len()), indent)?;
+        }
+        Ok(())
+    }
+}
+
+fn take_number(text: &str) -> Option<(usize, &str)> {
+    let end = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let number = text[..end].parse().ok()?;
+    Some((number, &text[end..]))
+}
+
+/// Parse the location prefix drivers put in front of each message. Handles
+/// `0:12(5): ...` (Mesa), `0(12) : ...` (NVIDIA) and `0:12: ...` (AMD, Intel).
+fn parse_location(text: &str) -> Option<(usize, Option<usize>, &str)> {
+    let (_source_string, rest) = take_number(text)?;
+    let (line, rest) = if let Some(rest) = rest.strip_prefix(':') {
+        take_number(rest)?
+    } else {
+        let (line, rest) = take_number(rest.strip_prefix('(')?)?;
+        (line, rest.strip_prefix(')')?)
+    };
+    let (column, rest) = match rest.strip_prefix('(').and_then(take_number) {
+        Some((column, rest)) => (Some(column), rest.strip_prefix(')')?),
+        None => (None, rest),
+    };
+    Some((line, column, rest.trim_start().strip_prefix(':')?.trim_start()))
+}
+
+fn strip_severity(text: &str) -> (Option<Severity>, &str) {
+    let lowercase = text.to_ascii_lowercase();
+    let severity = if lowercase.starts_with("error") {
+        Severity::Error
+    } else if lowercase.starts_with("warning") {
+        Severity::Warning
+    } else {
+        return (None, text);
+    };
+    // Drop the severity word and anything up to its colon, e.g. "error C0000:"
+    match text.find(':') {
+        Some(colon) if text[..colon].split_whitespace().count() <= 2 => {
+            (Some(severity), text[colon + 1..].trim_start())
+        },
+        _ => (Some(severity), text),
+    }
+}
+
+/// Split a compiler info log into diagnostics, mapping the reported lines of the
+/// preprocessed source back to the file and line they were written in.
+pub fn parse_log(log: &str, preprocessed: &Preprocessed) -> Vec<Diagnostic> {
+    log.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (prefix_severity, line) = strip_severity(line);
+            let (location, message) = match parse_location(line) {
+                Some((line, column, message)) => (Some((line, column)), message),
+                None => (None, line),
+            };
+            let (severity, message) = match strip_severity(message) {
+                (Some(severity), message) => (Some(severity), message),
+                (None, message) => (prefix_severity, message),
+            };
+
+            let mut diagnostic = Diagnostic {
+                severity: severity.unwrap_or(Severity::Error),
+                path: None,
+                line: None,
+                column: None,
+                message: message.to_string(),
+                source_line: None,
+            };
+            if let Some((line, column)) = location {
+                let mapped = line.checked_sub(1).and_then(|index| preprocessed.line_map.get(index));
+                if let Some(mapped) = mapped {
+                    let unit = &preprocessed.units[mapped.unit];
+                    diagnostic.path = unit.path.clone();
+                    diagnostic.line = Some(mapped.line);
+                    diagnostic.column = column;
+                    diagnostic.source_line = unit.text.lines().nth(mapped.line - 1).map(str::to_string);
+                } else {
+                    diagnostic.path = preprocessed.units.first().and_then(|unit| unit.path.clone());
+                    diagnostic.line = Some(line);
+                }
+            }
+            diagnostic
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, fs};
+
+    use crate::shader::preprocess;
+
+    use super::*;
+
+    const STAGE: &str = "#version 450 core\n#include \"common.glsl\"\nout vec4 color;\nvoid main() { color = tint(); }\n";
+    const COMMON: &str = "#pragma once\nvec4 tint() { return vec4(1.0) }\n";
+
+    /// `STAGE` preprocessed with `common.glsl` next to it and one injected define, so
+    /// the source lines are: `#version`, `#define`, `tint`, `out`, `main`.
+    fn preprocessed(name: &str) -> Preprocessed {
+        let dir = std::env::temp_dir().join(format!("gloom-rs-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("common.glsl"), COMMON).unwrap();
+        let mut preprocessed = preprocess::preprocess(STAGE, None, std::slice::from_ref(&dir), true).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let defines: BTreeMap<String, String> = [("TINTED".to_string(), "1".to_string())].into();
+        preprocessed.inject_defines(&defines);
+        preprocessed
+    }
+
+    fn file_name(diagnostic: &Diagnostic) -> Option<String> {
+        diagnostic.path.as_ref()
+            .and_then(|path| path.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+    }
+
+    #[test]
+    fn mesa_lines_in_an_included_file() {
+        let diagnostics = parse_log("0:3(33): error: syntax error, unexpected '}'\n", &preprocessed("mesa"));
+        assert_eq!(diagnostics.len(), 1);
+        let diagnostic = &diagnostics[0];
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(file_name(diagnostic).as_deref(), Some("common.glsl"));
+        assert_eq!(diagnostic.line, Some(2));
+        assert_eq!(diagnostic.column, Some(33));
+        assert_eq!(diagnostic.message, "syntax error, unexpected '}'");
+        assert_eq!(diagnostic.source_line.as_deref(), Some("vec4 tint() { return vec4(1.0) }"));
+    }
+
+    #[test]
+    fn nvidia_lines_after_injected_defines() {
+        let log = "0(5) : error C1008: undefined variable \"colour\"\n0(4) : warning C7050: \"color\" might be used before being initialized";
+        let diagnostics = parse_log(log, &preprocessed("nvidia"));
+        assert_eq!(diagnostics.len(), 2);
+
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].path, None);
+        assert_eq!(diagnostics[0].line, Some(4));
+        assert_eq!(diagnostics[0].column, None);
+        assert_eq!(diagnostics[0].message, "undefined variable \"colour\"");
+        assert_eq!(diagnostics[0].source_line.as_deref(), Some("void main() { color = tint(); }"));
+
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+        assert_eq!(diagnostics[1].line, Some(3));
+    }
+
+    #[test]
+    fn amd_severity_prefix() {
+        let log = "ERROR: 0:5: 'colour' : undeclared identifier\nWARNING: 0:2: 'TINTED' : unused macro\nERROR: 1 compilation errors.  No code generated.";
+        let diagnostics = parse_log(log, &preprocessed("amd"));
+        assert_eq!(diagnostics.len(), 3);
+
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].path, None);
+        assert_eq!(diagnostics[0].line, Some(4));
+        assert_eq!(diagnostics[0].message, "'colour' : undeclared identifier");
+
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+        assert_eq!(file_name(&diagnostics[1]).as_deref(), Some("<defines>"));
+        assert_eq!(diagnostics[1].line, Some(1));
+        assert_eq!(diagnostics[1].source_line.as_deref(), Some("#define TINTED 1"));
+
+        assert_eq!(diagnostics[2].severity, Severity::Error);
+        assert_eq!(diagnostics[2].line, None);
+        assert_eq!(diagnostics[2].message, "1 compilation errors.  No code generated.");
+    }
+
+    #[test]
+    fn lines_past_the_end_are_kept_as_reported() {
+        let diagnostics = parse_log("0:42: error: unexpected end of file", &preprocessed("past-end"));
+        assert_eq!(diagnostics[0].path, None);
+        assert_eq!(diagnostics[0].line, Some(42));
+        assert_eq!(diagnostics[0].source_line, None);
+    }
+}
@@ -0,0 +1,58 @@
+use std::{
+    fs,
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+/// Every file under `shaders/`, keyed by its path relative to the repository root,
+/// e.g. `"shaders/simple.vert"`. Generated by `build.rs`.
+static EMBEDDED: &[(&str, &str)] = include!(concat!(env!("OUT_DIR"), "/embedded_shaders.rs"));
+
+/// `path` with `.` and `..` resolved lexically and joined with forward slashes, so
+/// `./shaders/lib/../simple.vert` finds `shaders/simple.vert`. Absolute paths inside
+/// the repository are made relative to it.
+pub fn normalize(path: &Path) -> String {
+    let path = path.strip_prefix(env!("CARGO_MANIFEST_DIR")).unwrap_or(path);
+    let mut parts: Vec<String> = vec![];
+    for component in path.components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir if parts.last().is_some_and(|part| part != "..") => { parts.pop(); },
+            _ => { parts.push(component.as_os_str().to_string_lossy().into_owned()); },
+        }
+    }
+    parts.join("/")
+}
+
+/// The embedded source for `path`, if it was under `shaders/` at build time.
+pub fn get(path: &Path) -> Option<&'static str> {
+    let key = normalize(path);
+    EMBEDDED.iter()
+        .find(|(embedded_path, _)| *embedded_path == key)
+        .map(|(_, source)| *source)
+}
+
+/// Read a shader source, from the binary first unless `prefer_disk` is set. Either way
+/// the other location is the fallback, so a debug build started outside the repository
+/// still finds its shaders, and a release build can still load files it doesn't embed.
+pub fn read(path: &Path, prefer_disk: bool) -> io::Result<String> {
+    if !prefer_disk {
+        if let Some(source) = get(path) {
+            return Ok(source.to_string());
+        }
+    }
+    match fs::read_to_string(path) {
+        Err(error) if prefer_disk => get(path).map(str::to_string).ok_or(error),
+        result => result,
+    }
+}
+
+/// Whether `read` would find anything at `path`.
+pub fn exists(path: &Path) -> bool {
+    get(path).is_some() || path.is_file()
+}
+
+/// A key identifying the file at `path`, equal for every way of spelling the same path.
+pub fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(normalize(path)))
+}
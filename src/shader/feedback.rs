@@ -0,0 +1,137 @@
+use crate::gl_object::{Buffer, Query, TransformFeedback};
+
+use super::Shader;
+
+/// The primitives captured by transform feedback. Draw calls must use a matching
+/// mode, e.g. `Triangles` also accepts strips and fans, which are captured as
+/// separate triangles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedbackPrimitive {
+    Points,
+    Lines,
+    Triangles,
+}
+
+impl FeedbackPrimitive {
+    fn vertices(self) -> usize {
+        match self {
+            FeedbackPrimitive::Points    => { 1 },
+            FeedbackPrimitive::Lines     => { 2 },
+            FeedbackPrimitive::Triangles => { 3 },
+        }
+    }
+}
+
+impl From<FeedbackPrimitive> for gl::types::GLenum {
+    fn from(primitive: FeedbackPrimitive) -> Self {
+        match primitive {
+            FeedbackPrimitive::Points    => { gl::POINTS    },
+            FeedbackPrimitive::Lines     => { gl::LINES     },
+            FeedbackPrimitive::Triangles => { gl::TRIANGLES },
+        }
+    }
+}
+
+/// Buffers that the varyings declared with `ShaderBuilder::feedback_varyings` are
+/// captured into, sized for a fixed number of vertices.
+///
+/// The buffers are ordinary buffer objects, so they can also be bound as vertex input
+/// for the next step of a simulation running entirely on the GPU.
+pub struct FeedbackCapture {
+    transform_feedback: TransformFeedback,
+    // One buffer per feedback binding, with the bytes written per vertex
+    buffers: Vec<(Buffer, usize)>,
+    capacity: usize,
+    captured: usize,
+    written: Query,
+    generated: Query,
+}
+
+impl FeedbackCapture {
+    /// Buffers for up to `capacity` vertices from `shader`. Panics if the program
+    /// captures no varyings.
+    pub unsafe fn new(shader: &Shader, capacity: usize) -> FeedbackCapture {
+        let layouts = &shader.interface().feedback_buffers;
+        assert!(!layouts.is_empty(), "program {} has no transform feedback varyings", shader.program_id());
+
+        let transform_feedback = TransformFeedback::new();
+        let buffers = layouts.iter()
+            .map(|layout| {
+                let buffer = Buffer::new();
+                let stride = layout.stride as usize;
+                gl::NamedBufferData(buffer.id(), (capacity.max(1) * stride) as isize, std::ptr::null(), gl::DYNAMIC_COPY);
+                gl::TransformFeedbackBufferBase(transform_feedback.id(), layout.binding, buffer.id());
+                (buffer, stride)
+            })
+            .collect();
+
+        FeedbackCapture {
+            transform_feedback,
+            buffers,
+            capacity,
+            captured: 0,
+            written: Query::new(gl::TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN),
+            generated: Query::new(gl::PRIMITIVES_GENERATED),
+        }
+    }
+
+    /// Activate `shader` and run `draw` with transform feedback active, replacing
+    /// anything captured before. Unless `rasterize` is set, nothing reaches the
+    /// framebuffer. Returns the number of vertices captured.
+    ///
+    /// This waits for the GPU to finish the draw, to know how much was written.
+    pub unsafe fn capture<F: FnOnce()>(&mut self, shader: &Shader, primitive: FeedbackPrimitive, rasterize: bool, draw: F) -> usize {
+        shader.activate();
+        self.transform_feedback.bind();
+        if !rasterize {
+            gl::Enable(gl::RASTERIZER_DISCARD);
+        }
+        gl::BeginQuery(gl::TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN, self.written.id());
+        gl::BeginQuery(gl::PRIMITIVES_GENERATED, self.generated.id());
+        gl::BeginTransformFeedback(primitive.into());
+
+        draw();
+
+        gl::EndTransformFeedback();
+        gl::EndQuery(gl::PRIMITIVES_GENERATED);
+        gl::EndQuery(gl::TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN);
+        if !rasterize {
+            gl::Disable(gl::RASTERIZER_DISCARD);
+        }
+        gl::BindTransformFeedback(gl::TRANSFORM_FEEDBACK, 0);
+
+        let written = self.written.result() as usize;
+        let generated = self.generated.result() as usize;
+        if generated > written {
+            println!("WARNING: transform feedback buffers hold {} vertices, {} primitives were dropped",
+                self.capacity, generated - written);
+        }
+        self.captured = written * primitive.vertices();
+        self.captured
+    }
+
+    /// The number of vertices written by the last `capture`.
+    pub fn captured(&self) -> usize {
+        self.captured
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The buffer for feedback binding `index`, e.g. to draw from it.
+    pub fn buffer(&self, index: usize) -> &Buffer {
+        &self.buffers[index].0
+    }
+
+    /// Copy the vertices captured into binding `index` back to the CPU. `T` must have
+    /// the same size as one vertex in that buffer, e.g. `glm::Vec4` for a single `vec4`.
+    pub unsafe fn read<T: Copy>(&self, index: usize) -> Vec<T> {
+        let (buffer, stride) = &self.buffers[index];
+        assert_eq!(std::mem::size_of::<T>(), *stride, "size of T doesn't match the feedback buffer's vertex stride");
+        let mut vertices: Vec<T> = Vec::with_capacity(self.captured);
+        gl::GetNamedBufferSubData(buffer.id(), 0, (self.captured * stride) as isize, vertices.as_mut_ptr() as *mut _);
+        vertices.set_len(self.captured);
+        vertices
+    }
+}
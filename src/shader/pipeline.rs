@@ -0,0 +1,88 @@
+use crate::gl_object::ProgramPipeline;
+
+use super::{Shader, ShaderError, ShaderType};
+
+/// A program pipeline, drawing with stages taken from several separable programs.
+///
+/// The programs are borrowed, so they outlive the pipeline. Uniforms are still set on
+/// the program that declares them, e.g. `pipeline.program(ShaderType::Fragment)`.
+/// With separable programs, vertex outputs must match fragment inputs by location or
+/// exact declaration, and the vertex stage should redeclare `out gl_PerVertex`.
+pub struct Pipeline<'a> {
+    pipeline: ProgramPipeline,
+    programs: Vec<(ShaderType, &'a Shader)>,
+}
+
+impl<'a> Pipeline<'a> {
+    pub unsafe fn new() -> Pipeline<'a> {
+        Pipeline { pipeline: ProgramPipeline::new(), programs: vec![] }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.pipeline.id()
+    }
+
+    /// Use every stage of `shader` in this pipeline, replacing whichever programs
+    /// provided those stages before.
+    pub unsafe fn use_program(&mut self, shader: &'a Shader) -> Result<(), ShaderError> {
+        if !shader.is_separable() {
+            return Err(ShaderError::NotSeparable);
+        }
+        let mut stage_bits = 0;
+        for stage in shader.stages() {
+            stage_bits |= stage.stage_bit();
+            self.programs.retain(|(shader_type, _)| *shader_type != stage);
+            self.programs.push((stage, shader));
+        }
+        gl::UseProgramStages(self.pipeline.id(), stage_bits, shader.program_id());
+        Ok(())
+    }
+
+    /// Builder-style `use_program`, for setting up a pipeline in one expression.
+    pub unsafe fn with_program(mut self, shader: &'a Shader) -> Result<Pipeline<'a>, ShaderError> {
+        self.use_program(shader)?;
+        Ok(self)
+    }
+
+    /// Stop using any program for `stage`.
+    pub unsafe fn clear_stage(&mut self, stage: ShaderType) {
+        self.programs.retain(|(shader_type, _)| *shader_type != stage);
+        gl::UseProgramStages(self.pipeline.id(), stage.stage_bit(), 0);
+    }
+
+    /// The program currently providing `stage`, if any.
+    pub fn program(&self, stage: ShaderType) -> Option<&'a Shader> {
+        self.programs.iter()
+            .find(|(shader_type, _)| *shader_type == stage)
+            .map(|(_, shader)| *shader)
+    }
+
+    /// Ask the driver whether the current combination of stages can be drawn with.
+    pub unsafe fn validate(&self) -> Result<(), ShaderError> {
+        gl::ValidateProgramPipeline(self.pipeline.id());
+        let mut success = i32::from(gl::FALSE);
+        gl::GetProgramPipelineiv(self.pipeline.id(), gl::VALIDATE_STATUS, &mut success);
+        if success == i32::from(gl::TRUE) {
+            return Ok(());
+        }
+
+        let mut log_length = 0;
+        gl::GetProgramPipelineiv(self.pipeline.id(), gl::INFO_LOG_LENGTH, &mut log_length);
+        let mut info_log = vec![0u8; log_length.max(1) as usize];
+        gl::GetProgramPipelineInfoLog(
+            self.pipeline.id(),
+            info_log.len() as i32,
+            &mut log_length,
+            info_log.as_mut_ptr() as *mut gl::types::GLchar,
+        );
+        info_log.truncate(log_length as usize);
+        Err(ShaderError::Pipeline { log: String::from_utf8_lossy(&info_log).into_owned() })
+    }
+
+    /// Draw with this pipeline from now on. A program made current with
+    /// `Shader::activate` takes precedence over any pipeline, so this clears it.
+    pub unsafe fn bind(&self) {
+        gl::UseProgram(0);
+        gl::BindProgramPipeline(self.pipeline.id());
+    }
+}
@@ -0,0 +1,238 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use super::{embedded, ShaderError};
+
+/// One file (or inline string) that contributed lines to a preprocessed stage.
+#[derive(Debug, Clone)]
+pub struct SourceUnit {
+    pub path: Option<PathBuf>,
+    pub text: String,
+}
+
+/// Where a line of preprocessed output originally came from.
+#[derive(Debug, Clone, Copy)]
+pub struct SourceLine {
+    pub unit: usize,
+    /// 1-based, like the line numbers in compiler logs.
+    pub line: usize,
+}
+
+/// The result of resolving every `#include` in a stage.
+#[derive(Debug, Clone, Default)]
+pub struct Preprocessed {
+    pub source: String,
+    pub units: Vec<SourceUnit>,
+    /// One entry per line of `source`.
+    pub line_map: Vec<SourceLine>,
+}
+
+impl Preprocessed {
+    /// Every file read while preprocessing, root first.
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.units.iter().filter_map(|unit| unit.path.as_deref())
+    }
+
+    /// Insert a `#define` for each entry right after the `#version` line, or at the
+    /// very top if there is none. The injected lines map to a `<defines>` pseudo-file,
+    /// so call this after collecting `files`.
+    pub fn inject_defines(&mut self, defines: &BTreeMap<String, String>) {
+        if defines.is_empty() {
+            return;
+        }
+        let text: String = defines.iter()
+            .map(|(name, value)| format!("#define {} {}\n", name, value))
+            .collect();
+        let unit = self.units.len();
+        let injected_lines = (1..=defines.len()).map(|line| SourceLine { unit, line });
+        self.units.push(SourceUnit { path: Some(PathBuf::from("<defines>")), text: text.clone() });
+
+        let mut lines: Vec<&str> = self.source.lines().collect();
+        let insert_at = lines.iter()
+            .position(|line| line.trim_start().starts_with("#version"))
+            .map_or(0, |index| index + 1);
+        lines.splice(insert_at..insert_at, text.lines());
+        self.source = lines.iter().map(|line| format!("{}\n", line)).collect();
+        self.line_map.splice(insert_at..insert_at, injected_lines);
+    }
+}
+
+struct Preprocessor<'a> {
+    include_dirs: &'a [PathBuf],
+    prefer_disk: bool,
+    output: Preprocessed,
+    stack: Vec<PathBuf>,
+    included_once: HashSet<PathBuf>,
+}
+
+/// Resolve `#include "file"` directives in `text`.
+///
+/// Includes are looked up relative to the including file first, then in each of
+/// `include_dirs` in order. `#pragma once` skips a file that has already been
+/// included, and including a file that is still being processed is an error.
+/// Included files are read like stages are, see `embedded::read`.
+pub fn preprocess(text: &str, path: Option<&Path>, include_dirs: &[PathBuf], prefer_disk: bool) -> Result<Preprocessed, ShaderError> {
+    let mut preprocessor = Preprocessor {
+        include_dirs,
+        prefer_disk,
+        output: Preprocessed::default(),
+        stack: vec![],
+        included_once: HashSet::new(),
+    };
+    preprocessor.process(text, path)?;
+    Ok(preprocessor.output)
+}
+
+fn parse_include(line: &str) -> Option<&str> {
+    let directive = line.trim_start().strip_prefix('#')?.trim_start();
+    let target = directive.strip_prefix("include")?.trim();
+    let target = target.strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .or_else(|| target.strip_prefix('<').and_then(|rest| rest.strip_suffix('>')))?;
+    Some(target)
+}
+
+fn is_pragma_once(line: &str) -> bool {
+    line.trim_start()
+        .strip_prefix('#')
+        .map(|directive| directive.split_whitespace().eq(["pragma", "once"]))
+        .unwrap_or(false)
+}
+
+impl<'a> Preprocessor<'a> {
+    fn process(&mut self, text: &str, path: Option<&Path>) -> Result<(), ShaderError> {
+        let unit = self.output.units.len();
+        self.output.units.push(SourceUnit {
+            path: path.map(Path::to_path_buf),
+            text: text.to_string(),
+        });
+        let key = path.map(embedded::canonical);
+        if let Some(key) = &key {
+            self.stack.push(key.clone());
+        }
+
+        for (index, line) in text.lines().enumerate() {
+            if let Some(target) = parse_include(line) {
+                let include_path = self.resolve(target, path)
+                    .ok_or_else(|| ShaderError::Include {
+                        path: path.map(Path::to_path_buf),
+                        line: index + 1,
+                        target: target.to_string(),
+                    })?;
+                let include_key = embedded::canonical(&include_path);
+                if self.stack.contains(&include_key) {
+                    let mut chain = self.stack.clone();
+                    chain.push(include_key);
+                    return Err(ShaderError::IncludeCycle(chain));
+                }
+                if self.included_once.contains(&include_key) {
+                    continue;
+                }
+                let include_text = embedded::read(&include_path, self.prefer_disk)
+                    .map_err(|source| ShaderError::Io { path: include_path.clone(), source })?;
+                self.process(&include_text, Some(&include_path))?;
+            } else if is_pragma_once(line) {
+                if let Some(key) = &key {
+                    self.included_once.insert(key.clone());
+                }
+            } else {
+                self.output.source.push_str(line);
+                self.output.source.push('\n');
+                self.output.line_map.push(SourceLine { unit, line: index + 1 });
+            }
+        }
+
+        if key.is_some() {
+            self.stack.pop();
+        }
+        Ok(())
+    }
+
+    fn resolve(&self, target: &str, including: Option<&Path>) -> Option<PathBuf> {
+        let relative = including
+            .and_then(Path::parent)
+            .map(|dir| dir.join(target));
+        relative.into_iter()
+            .chain(self.include_dirs.iter().map(|dir| dir.join(target)))
+            .find(|candidate| embedded::exists(candidate))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/glsl").join(name)
+    }
+
+    fn preprocess_fixture(name: &str, include_dirs: &[PathBuf]) -> Result<Preprocessed, ShaderError> {
+        let path = fixture(name);
+        let text = fs::read_to_string(&path).unwrap();
+        preprocess(&text, Some(&path), include_dirs, true)
+    }
+
+    fn file_name(path: &Path) -> String {
+        path.file_name().unwrap().to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn include_cycles_are_errors() {
+        match preprocess_fixture("cycle/a.glsl", &[]) {
+            Err(ShaderError::IncludeCycle(chain)) => {
+                let names: Vec<String> = chain.iter().map(|path| file_name(path)).collect();
+                assert_eq!(names, ["a.glsl", "b.glsl", "a.glsl"]);
+            },
+            other => panic!("expected an include cycle, got {:?}", other.map(|output| output.source)),
+        }
+    }
+
+    #[test]
+    fn pragma_once_includes_a_diamond_once() {
+        let output = preprocess_fixture("diamond/main.frag", &[]).unwrap();
+        assert_eq!(output.source, "#version 450 core\n\
+            const float common_value = 1.0;\n\
+            float left() { return common_value; }\n\
+            float right() { return common_value; }\n\
+            void main() {}\n");
+
+        let units: Vec<String> = output.files().map(file_name).collect();
+        assert_eq!(units, ["main.frag", "left.glsl", "common.glsl", "right.glsl"]);
+        let lines: Vec<(usize, usize)> = output.line_map.iter().map(|line| (line.unit, line.line)).collect();
+        assert_eq!(lines, [(0, 1), (2, 2), (1, 2), (3, 2), (0, 4)]);
+    }
+
+    #[test]
+    fn includes_next_to_the_file_win_over_include_dirs() {
+        let output = preprocess_fixture("precedence/main.frag", &[fixture("precedence/dir")]).unwrap();
+        assert_eq!(output.source, "#version 450 core\n\
+            // shared.glsl next to main.frag\n\
+            // only_in_dir.glsl in the include directory\n");
+    }
+
+    #[test]
+    fn missing_includes_report_their_line() {
+        let text = "#version 450 core\n\nvoid main() {}\n#include \"missing.glsl\"\n";
+        match preprocess(text, None, &[fixture("precedence/dir")], true) {
+            Err(ShaderError::Include { path, line, target }) => {
+                assert_eq!(path, None);
+                assert_eq!(line, 4);
+                assert_eq!(target, "missing.glsl");
+            },
+            other => panic!("expected a missing include, got {:?}", other.map(|output| output.source)),
+        }
+
+        match preprocess_fixture("precedence/main.frag", &[]) {
+            Err(ShaderError::Include { path, line, target }) => {
+                assert_eq!(path, Some(fixture("precedence/main.frag")));
+                assert_eq!(line, 3);
+                assert_eq!(target, "only_in_dir.glsl");
+            },
+            other => panic!("expected a missing include, got {:?}", other.map(|output| output.source)),
+        }
+    }
+}
@@ -0,0 +1,355 @@
+use std::fmt;
+
+use gl::types::GLenum;
+
+/// Everything the linker reports as active in a program, queried once after linking.
+#[derive(Debug, Clone, Default)]
+pub struct ProgramInterface {
+    pub attributes: Vec<Attribute>,
+    /// Uniforms in the default block. Members of uniform blocks are listed on the block.
+    pub uniforms: Vec<Uniform>,
+    pub uniform_blocks: Vec<Block>,
+    pub storage_blocks: Vec<Block>,
+    /// Buffers written by transform feedback, in binding order.
+    pub feedback_buffers: Vec<FeedbackBuffer>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Attribute {
+    pub name: String,
+    pub gl_type: GLenum,
+    pub array_size: i32,
+    /// `-1` for built-ins such as `gl_VertexID`.
+    pub location: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Uniform {
+    pub name: String,
+    pub gl_type: GLenum,
+    pub array_size: i32,
+    pub location: i32,
+}
+
+/// A uniform block or a shader storage block.
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub name: String,
+    pub binding: u32,
+    /// Minimum size in bytes of a buffer bound to this block.
+    pub data_size: i32,
+    pub members: Vec<BlockMember>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BlockMember {
+    pub name: String,
+    pub gl_type: GLenum,
+    pub array_size: i32,
+    /// Byte offset from the start of the block.
+    pub offset: i32,
+    pub array_stride: i32,
+    pub matrix_stride: i32,
+    /// Stride of the outermost array this member is part of. Only reported for
+    /// storage block members, this is the element stride of a `buffer { T data[]; }`.
+    pub top_level_array_stride: i32,
+}
+
+/// One buffer binding written by transform feedback. A program in interleaved mode
+/// has one of these, in separate mode there is one per varying.
+#[derive(Debug, Clone)]
+pub struct FeedbackBuffer {
+    pub binding: u32,
+    /// Bytes written per vertex.
+    pub stride: i32,
+    pub varyings: Vec<FeedbackVarying>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FeedbackVarying {
+    pub name: String,
+    pub gl_type: GLenum,
+    pub array_size: i32,
+    /// Byte offset from the start of each vertex in the buffer.
+    pub offset: i32,
+}
+
+impl ProgramInterface {
+    /// Query every active resource of a successfully linked program.
+    pub unsafe fn query(program_id: u32) -> ProgramInterface {
+        let attributes = resources(program_id, gl::PROGRAM_INPUT, &[gl::TYPE, gl::ARRAY_SIZE, gl::LOCATION])
+            .map(|(name, values)| Attribute {
+                name,
+                gl_type: values[0] as GLenum,
+                array_size: values[1],
+                location: values[2],
+            })
+            .collect();
+        let uniforms = resources(program_id, gl::UNIFORM, &[gl::TYPE, gl::ARRAY_SIZE, gl::LOCATION, gl::BLOCK_INDEX])
+            .filter(|(_, values)| values[3] == -1)
+            .map(|(name, values)| Uniform {
+                name,
+                gl_type: values[0] as GLenum,
+                array_size: values[1],
+                location: values[2],
+            })
+            .collect();
+        ProgramInterface {
+            attributes,
+            uniforms,
+            uniform_blocks: blocks(program_id, gl::UNIFORM_BLOCK, gl::UNIFORM),
+            storage_blocks: blocks(program_id, gl::SHADER_STORAGE_BLOCK, gl::BUFFER_VARIABLE),
+            feedback_buffers: feedback_buffers(program_id),
+        }
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes.iter().find(|attribute| attribute.name == name)
+    }
+
+    pub fn uniform(&self, name: &str) -> Option<&Uniform> {
+        self.uniforms.iter().find(|uniform| uniform.name == name)
+    }
+
+    pub fn uniform_block(&self, name: &str) -> Option<&Block> {
+        self.uniform_blocks.iter().find(|block| block.name == name)
+    }
+
+    pub fn storage_block(&self, name: &str) -> Option<&Block> {
+        self.storage_blocks.iter().find(|block| block.name == name)
+    }
+}
+
+unsafe fn active_resources(program_id: u32, interface: GLenum) -> u32 {
+    let mut count = 0;
+    gl::GetProgramInterfaceiv(program_id, interface, gl::ACTIVE_RESOURCES, &mut count);
+    count as u32
+}
+
+unsafe fn resource_values(program_id: u32, interface: GLenum, index: u32, properties: &[GLenum]) -> Vec<i32> {
+    let mut values = vec![0; properties.len()];
+    gl::GetProgramResourceiv(
+        program_id, interface, index,
+        properties.len() as i32, properties.as_ptr(),
+        values.len() as i32, std::ptr::null_mut(), values.as_mut_ptr(),
+    );
+    values
+}
+
+unsafe fn resource_name(program_id: u32, interface: GLenum, index: u32) -> String {
+    let name_length = resource_values(program_id, interface, index, &[gl::NAME_LENGTH])[0];
+    let mut name = vec![0u8; name_length.max(1) as usize];
+    let mut length = 0;
+    gl::GetProgramResourceName(
+        program_id, interface, index,
+        name.len() as i32, &mut length,
+        name.as_mut_ptr() as *mut gl::types::GLchar,
+    );
+    name.truncate(length as usize);
+    String::from_utf8_lossy(&name).into_owned()
+}
+
+/// Name and the requested properties of every active resource in `interface`.
+unsafe fn resources<'a>(program_id: u32, interface: GLenum, properties: &'a [GLenum]) -> impl Iterator<Item = (String, Vec<i32>)> + 'a {
+    (0..active_resources(program_id, interface)).map(move |index| (
+        resource_name(program_id, interface, index),
+        resource_values(program_id, interface, index, properties),
+    ))
+}
+
+unsafe fn blocks(program_id: u32, block_interface: GLenum, member_interface: GLenum) -> Vec<Block> {
+    resources(program_id, block_interface, &[gl::BUFFER_BINDING, gl::BUFFER_DATA_SIZE, gl::NUM_ACTIVE_VARIABLES])
+        .enumerate()
+        .map(|(index, (name, values))| {
+            let mut member_indices = vec![0; values[2] as usize];
+            gl::GetProgramResourceiv(
+                program_id, block_interface, index as u32,
+                1, &gl::ACTIVE_VARIABLES,
+                member_indices.len() as i32, std::ptr::null_mut(), member_indices.as_mut_ptr(),
+            );
+            let mut members: Vec<BlockMember> = member_indices.iter()
+                .map(|&member| {
+                    let member = member as u32;
+                    let values = resource_values(
+                        program_id, member_interface, member,
+                        &[gl::TYPE, gl::ARRAY_SIZE, gl::OFFSET, gl::ARRAY_STRIDE, gl::MATRIX_STRIDE],
+                    );
+                    // Not a valid property for uniforms
+                    let top_level_array_stride = if member_interface == gl::BUFFER_VARIABLE {
+                        resource_values(program_id, member_interface, member, &[gl::TOP_LEVEL_ARRAY_STRIDE])[0]
+                    } else {
+                        0
+                    };
+                    BlockMember {
+                        name: resource_name(program_id, member_interface, member),
+                        gl_type: values[0] as GLenum,
+                        array_size: values[1],
+                        offset: values[2],
+                        array_stride: values[3],
+                        matrix_stride: values[4],
+                        top_level_array_stride,
+                    }
+                })
+                .collect();
+            members.sort_by_key(|member| member.offset);
+            Block {
+                name,
+                binding: values[0] as u32,
+                data_size: values[1],
+                members,
+            }
+        })
+        .collect()
+}
+
+unsafe fn feedback_buffers(program_id: u32) -> Vec<FeedbackBuffer> {
+    // Feedback buffers have no names, so `resources` can't be used here
+    let mut buffers: Vec<FeedbackBuffer> = (0..active_resources(program_id, gl::TRANSFORM_FEEDBACK_BUFFER))
+        .map(|index| {
+            let values = resource_values(
+                program_id, gl::TRANSFORM_FEEDBACK_BUFFER, index,
+                &[gl::BUFFER_BINDING, gl::TRANSFORM_FEEDBACK_BUFFER_STRIDE, gl::NUM_ACTIVE_VARIABLES],
+            );
+            let mut varying_indices = vec![0; values[2] as usize];
+            gl::GetProgramResourceiv(
+                program_id, gl::TRANSFORM_FEEDBACK_BUFFER, index,
+                1, &gl::ACTIVE_VARIABLES,
+                varying_indices.len() as i32, std::ptr::null_mut(), varying_indices.as_mut_ptr(),
+            );
+            let mut varyings: Vec<FeedbackVarying> = varying_indices.iter()
+                .map(|&varying| {
+                    let varying = varying as u32;
+                    let values = resource_values(
+                        program_id, gl::TRANSFORM_FEEDBACK_VARYING, varying,
+                        &[gl::TYPE, gl::ARRAY_SIZE, gl::OFFSET],
+                    );
+                    FeedbackVarying {
+                        name: resource_name(program_id, gl::TRANSFORM_FEEDBACK_VARYING, varying),
+                        gl_type: values[0] as GLenum,
+                        array_size: values[1],
+                        offset: values[2],
+                    }
+                })
+                .collect();
+            varyings.sort_by_key(|varying| varying.offset);
+            FeedbackBuffer { binding: values[0] as u32, stride: values[1], varyings }
+        })
+        .collect();
+    buffers.sort_by_key(|buffer| buffer.binding);
+    buffers
+}
+
+/// The GLSL spelling of a type returned by reflection, e.g. `"vec4"` for `gl::FLOAT_VEC4`.
+pub fn type_name(gl_type: GLenum) -> &'static str {
+    match gl_type {
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::DOUBLE => "double",
+        gl::DOUBLE_VEC2 => "dvec2",
+        gl::DOUBLE_VEC3 => "dvec3",
+        gl::DOUBLE_VEC4 => "dvec4",
+        gl::INT => "int",
+        gl::INT_VEC2 => "ivec2",
+        gl::INT_VEC3 => "ivec3",
+        gl::INT_VEC4 => "ivec4",
+        gl::UNSIGNED_INT => "uint",
+        gl::UNSIGNED_INT_VEC2 => "uvec2",
+        gl::UNSIGNED_INT_VEC3 => "uvec3",
+        gl::UNSIGNED_INT_VEC4 => "uvec4",
+        gl::BOOL => "bool",
+        gl::BOOL_VEC2 => "bvec2",
+        gl::BOOL_VEC3 => "bvec3",
+        gl::BOOL_VEC4 => "bvec4",
+        gl::FLOAT_MAT2 => "mat2",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::FLOAT_MAT2x3 => "mat2x3",
+        gl::FLOAT_MAT2x4 => "mat2x4",
+        gl::FLOAT_MAT3x2 => "mat3x2",
+        gl::FLOAT_MAT3x4 => "mat3x4",
+        gl::FLOAT_MAT4x2 => "mat4x2",
+        gl::FLOAT_MAT4x3 => "mat4x3",
+        gl::SAMPLER_1D => "sampler1D",
+        gl::SAMPLER_2D => "sampler2D",
+        gl::SAMPLER_3D => "sampler3D",
+        gl::SAMPLER_CUBE => "samplerCube",
+        gl::SAMPLER_2D_SHADOW => "sampler2DShadow",
+        gl::SAMPLER_2D_ARRAY => "sampler2DArray",
+        gl::INT_SAMPLER_2D => "isampler2D",
+        gl::UNSIGNED_INT_SAMPLER_2D => "usampler2D",
+        gl::IMAGE_2D => "image2D",
+        gl::IMAGE_3D => "image3D",
+        _ => "unknown",
+    }
+}
+
+/// The scalar type and number of scalars making up `gl_type`, e.g. `(gl::FLOAT, 16)` for a `mat4`.
+pub fn type_components(gl_type: GLenum) -> Option<(GLenum, i32)> {
+    Some(match gl_type {
+        gl::FLOAT => (gl::FLOAT, 1),
+        gl::FLOAT_VEC2 => (gl::FLOAT, 2),
+        gl::FLOAT_VEC3 => (gl::FLOAT, 3),
+        gl::FLOAT_VEC4 => (gl::FLOAT, 4),
+        gl::DOUBLE => (gl::DOUBLE, 1),
+        gl::DOUBLE_VEC2 => (gl::DOUBLE, 2),
+        gl::DOUBLE_VEC3 => (gl::DOUBLE, 3),
+        gl::DOUBLE_VEC4 => (gl::DOUBLE, 4),
+        gl::INT => (gl::INT, 1),
+        gl::INT_VEC2 => (gl::INT, 2),
+        gl::INT_VEC3 => (gl::INT, 3),
+        gl::INT_VEC4 => (gl::INT, 4),
+        gl::UNSIGNED_INT => (gl::UNSIGNED_INT, 1),
+        gl::UNSIGNED_INT_VEC2 => (gl::UNSIGNED_INT, 2),
+        gl::UNSIGNED_INT_VEC3 => (gl::UNSIGNED_INT, 3),
+        gl::UNSIGNED_INT_VEC4 => (gl::UNSIGNED_INT, 4),
+        gl::BOOL => (gl::BOOL, 1),
+        gl::BOOL_VEC2 => (gl::BOOL, 2),
+        gl::BOOL_VEC3 => (gl::BOOL, 3),
+        gl::BOOL_VEC4 => (gl::BOOL, 4),
+        gl::FLOAT_MAT2 => (gl::FLOAT, 4),
+        gl::FLOAT_MAT3 => (gl::FLOAT, 9),
+        gl::FLOAT_MAT4 => (gl::FLOAT, 16),
+        gl::FLOAT_MAT2x3 | gl::FLOAT_MAT3x2 => (gl::FLOAT, 6),
+        gl::FLOAT_MAT2x4 | gl::FLOAT_MAT4x2 => (gl::FLOAT, 8),
+        gl::FLOAT_MAT3x4 | gl::FLOAT_MAT4x3 => (gl::FLOAT, 12),
+        _ => return None,
+    })
+}
+
+fn array_suffix(array_size: i32) -> String {
+    if array_size > 1 { format!("[{}]", array_size) } else { String::new() }
+}
+
+impl fmt::Display for ProgramInterface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Attributes:")?;
+        for attribute in &self.attributes {
+            writeln!(f, "  layout(location={}) in {} {}{}", attribute.location,
+                type_name(attribute.gl_type), attribute.name, array_suffix(attribute.array_size))?;
+        }
+        writeln!(f, "Uniforms:")?;
+        for uniform in &self.uniforms {
+            writeln!(f, "  layout(location={}) uniform {} {}{}", uniform.location,
+                type_name(uniform.gl_type), uniform.name, array_suffix(uniform.array_size))?;
+        }
+        for (kind, blocks) in [("uniform", &self.uniform_blocks), ("buffer", &self.storage_blocks)] {
+            for block in blocks {
+                writeln!(f, "layout(binding={}) {} {} ({} bytes)", block.binding, kind, block.name, block.data_size)?;
+                for member in &block.members {
+                    writeln!(f, "  /* offset {:4} */ {} {}{}", member.offset,
+                        type_name(member.gl_type), member.name, array_suffix(member.array_size))?;
+                }
+            }
+        }
+        for buffer in &self.feedback_buffers {
+            writeln!(f, "Transform feedback buffer {} ({} bytes per vertex)", buffer.binding, buffer.stride)?;
+            for varying in &buffer.varyings {
+                writeln!(f, "  /* offset {:4} */ {} {}{}", varying.offset,
+                    type_name(varying.gl_type), varying.name, array_suffix(varying.array_size))?;
+            }
+        }
+        Ok(())
+    }
+}
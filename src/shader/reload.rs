@@ -0,0 +1,68 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use super::{Shader, ShaderError};
+
+/// A `Shader` that recompiles itself when any of its source files, or any file they
+/// `#include`, change on disk.
+///
+/// Call `reload_if_changed` once per frame. The program is only swapped out when
+/// every stage compiles and the program links, so a typo in a shader leaves the
+/// previous program running.
+pub struct ReloadableShader {
+    shader: Shader,
+    watched: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+impl ReloadableShader {
+    pub fn new(shader: Shader) -> ReloadableShader {
+        let watched = Self::watch(&shader);
+        ReloadableShader { shader, watched }
+    }
+
+    fn watch(shader: &Shader) -> Vec<(PathBuf, Option<SystemTime>)> {
+        shader.dependencies().iter()
+            .map(|path| (path.clone(), modified_time(path)))
+            .collect()
+    }
+
+    pub fn shader(&self) -> &Shader {
+        &self.shader
+    }
+
+    pub fn program_id(&self) -> u32 {
+        self.shader.program_id()
+    }
+
+    /// Returns `Ok(true)` if the program was rebuilt and swapped in, and `Ok(false)`
+    /// if nothing changed. On error the previous program is kept.
+    ///
+    /// The new program is *not* activated, call `activate` on it afterwards.
+    pub unsafe fn reload_if_changed(&mut self) -> Result<bool, ShaderError> {
+        let mut changed = false;
+        for (path, last_modified) in self.watched.iter_mut() {
+            let modified = modified_time(path);
+            if modified != *last_modified {
+                *last_modified = modified;
+                changed = true;
+            }
+        }
+        if !changed {
+            return Ok(false);
+        }
+
+        let shader = self.shader.rebuild()?;
+        // The new sources may include a different set of files
+        self.watched = Self::watch(&shader);
+        // Dropping the old shader deletes its program
+        self.shader = shader;
+        Ok(true)
+    }
+}
@@ -0,0 +1,74 @@
+use gl::types::GLint;
+
+/// A value that can be uploaded to a uniform with `Shader::set_uniform`.
+pub trait UniformValue {
+    unsafe fn upload(&self, program_id: u32, location: GLint);
+}
+
+/// Types with a matching `glProgramUniform*v` call, so single values and arrays
+/// of them can share one upload path.
+pub trait UniformElement: Sized {
+    unsafe fn upload_slice(values: &[Self], program_id: u32, location: GLint);
+}
+
+impl<T: UniformElement> UniformValue for T {
+    unsafe fn upload(&self, program_id: u32, location: GLint) {
+        T::upload_slice(std::slice::from_ref(self), program_id, location);
+    }
+}
+
+impl<T: UniformElement> UniformValue for [T] {
+    unsafe fn upload(&self, program_id: u32, location: GLint) {
+        T::upload_slice(self, program_id, location);
+    }
+}
+
+impl<T: UniformElement, const N: usize> UniformValue for [T; N] {
+    unsafe fn upload(&self, program_id: u32, location: GLint) {
+        T::upload_slice(&self[..], program_id, location);
+    }
+}
+
+macro_rules! uniform_vector {
+    ($type:ty, $scalar:ty, $function:ident) => {
+        impl UniformElement for $type {
+            unsafe fn upload_slice(values: &[Self], program_id: u32, location: GLint) {
+                gl::$function(program_id, location, values.len() as i32, values.as_ptr() as *const $scalar);
+            }
+        }
+    };
+}
+
+macro_rules! uniform_matrix {
+    ($type:ty, $function:ident) => {
+        impl UniformElement for $type {
+            unsafe fn upload_slice(values: &[Self], program_id: u32, location: GLint) {
+                // glm matrices are column major, like GLSL, so no transpose
+                gl::$function(program_id, location, values.len() as i32, gl::FALSE, values.as_ptr() as *const f32);
+            }
+        }
+    };
+}
+
+uniform_vector!(f32, f32, ProgramUniform1fv);
+uniform_vector!(glm::Vec2, f32, ProgramUniform2fv);
+uniform_vector!(glm::Vec3, f32, ProgramUniform3fv);
+uniform_vector!(glm::Vec4, f32, ProgramUniform4fv);
+uniform_vector!(i32, i32, ProgramUniform1iv);
+uniform_vector!(glm::IVec2, i32, ProgramUniform2iv);
+uniform_vector!(glm::IVec3, i32, ProgramUniform3iv);
+uniform_vector!(glm::IVec4, i32, ProgramUniform4iv);
+uniform_vector!(u32, u32, ProgramUniform1uiv);
+uniform_vector!(glm::UVec2, u32, ProgramUniform2uiv);
+uniform_vector!(glm::UVec3, u32, ProgramUniform3uiv);
+uniform_vector!(glm::UVec4, u32, ProgramUniform4uiv);
+uniform_matrix!(glm::Mat2, ProgramUniformMatrix2fv);
+uniform_matrix!(glm::Mat3, ProgramUniformMatrix3fv);
+uniform_matrix!(glm::Mat4, ProgramUniformMatrix4fv);
+
+impl UniformElement for bool {
+    unsafe fn upload_slice(values: &[Self], program_id: u32, location: GLint) {
+        let values: Vec<i32> = values.iter().map(|&value| value as i32).collect();
+        i32::upload_slice(&values, program_id, location);
+    }
+}
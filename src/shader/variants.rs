@@ -0,0 +1,55 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::{Shader, ShaderBuilder, ShaderError};
+
+/// A set of `#define`s identifying one variant. Sorted, so the same defines given
+/// in a different order share a variant.
+pub type Defines = BTreeMap<String, String>;
+
+/// Builds and keeps one `Shader` per set of defines, all from the same sources.
+///
+/// ```ignore
+/// let mut variants = ShaderVariants::new(|| unsafe {
+///     ShaderBuilder::new()
+///         .attach_file("./shaders/simple.vert")?
+///         .attach_file("./shaders/simple.frag")
+/// });
+/// let textured = unsafe { variants.get(&[("USE_TEXTURES", ""), ("LIGHTING", "1")])? };
+/// ```
+pub struct ShaderVariants {
+    make_builder: Box<dyn Fn() -> Result<ShaderBuilder, ShaderError>>,
+    variants: HashMap<Defines, Shader>,
+}
+
+impl ShaderVariants {
+    /// `make_builder` is called once per new variant and should attach every stage.
+    /// The variant's defines are added to whatever the returned builder defines itself.
+    pub fn new<F>(make_builder: F) -> ShaderVariants
+        where F: Fn() -> Result<ShaderBuilder, ShaderError> + 'static
+    {
+        ShaderVariants {
+            make_builder: Box::new(make_builder),
+            variants: HashMap::new(),
+        }
+    }
+
+    /// The variant for `defines`, building it on first request. Failed builds are not
+    /// remembered, so the next request tries again.
+    pub unsafe fn get(&mut self, defines: &[(&str, &str)]) -> Result<&Shader, ShaderError> {
+        let key: Defines = defines.iter()
+            .map(|&(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        if !self.variants.contains_key(&key) {
+            let shader = (self.make_builder)()?
+                .defines(defines.iter().copied())
+                .link()?;
+            self.variants.insert(key.clone(), shader);
+        }
+        Ok(&self.variants[&key])
+    }
+
+    /// Every variant built so far.
+    pub fn iter(&self) -> impl Iterator<Item = (&Defines, &Shader)> {
+        self.variants.iter()
+    }
+}
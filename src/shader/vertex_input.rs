@@ -0,0 +1,225 @@
+use std::fmt;
+
+use gl::types::GLenum;
+
+use super::reflection::{type_name, ProgramInterface};
+
+/// How a vertex attribute is fed to or read by the shader. Float attributes may be
+/// sourced from integer data through `glVertexAttribPointer`, but integer and double
+/// attributes need `glVertexAttribIPointer` and `glVertexAttribLPointer` respectively.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeKind {
+    Float,
+    Integer,
+    Double,
+}
+
+/// One enabled attribute array of a vertex array object.
+#[derive(Debug, Clone, Copy)]
+pub struct VertexArrayAttribute {
+    pub location: u32,
+    pub components: i32,
+    pub gl_type: GLenum,
+    pub kind: AttributeKind,
+}
+
+/// A difference between what a vertex array object provides and what a program reads.
+#[derive(Debug, Clone)]
+pub enum AttributeMismatch {
+    /// The shader reads a location the VAO has not enabled, so it sees a constant value.
+    NotEnabled { location: u32, name: String },
+    /// The VAO provides more components than the shader reads, likely the wrong attribute.
+    ComponentCount { location: u32, name: String, vao: i32, shader: i32 },
+    /// The data is fed with the wrong `glVertexAttrib*Pointer` variant for the shader's type.
+    Kind { location: u32, name: String, vao: AttributeKind, shader: AttributeKind },
+    /// The VAO enables a location no active attribute reads, often an off-by-one `location`.
+    NotInShader { location: u32 },
+}
+
+impl fmt::Display for AttributeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttributeMismatch::NotEnabled { location, name } => {
+                write!(f, "location {} (\"{}\") is read by the shader but not enabled in the VAO", location, name)
+            },
+            AttributeMismatch::ComponentCount { location, name, vao, shader } => {
+                write!(f, "location {} (\"{}\") has {} components in the VAO but the shader reads {}", location, name, vao, shader)
+            },
+            AttributeMismatch::Kind { location, name, vao, shader } => {
+                write!(f, "location {} (\"{}\") is fed as {:?} data but the shader reads it as {:?}", location, name, vao, shader)
+            },
+            AttributeMismatch::NotInShader { location } => {
+                write!(f, "location {} is enabled in the VAO but no active shader attribute uses it", location)
+            },
+        }
+    }
+}
+
+/// Every enabled attribute array of `vao`.
+pub unsafe fn vertex_array_attributes(vao: u32) -> Vec<VertexArrayAttribute> {
+    let mut max_attributes = 0;
+    gl::GetIntegerv(gl::MAX_VERTEX_ATTRIBS, &mut max_attributes);
+
+    let query = |location: u32, parameter: GLenum| {
+        let mut value = 0;
+        gl::GetVertexArrayIndexediv(vao, location, parameter, &mut value);
+        value
+    };
+    (0..max_attributes as u32)
+        .filter(|&location| query(location, gl::VERTEX_ATTRIB_ARRAY_ENABLED) != 0)
+        .map(|location| VertexArrayAttribute {
+            location,
+            components: query(location, gl::VERTEX_ATTRIB_ARRAY_SIZE),
+            gl_type: query(location, gl::VERTEX_ATTRIB_ARRAY_TYPE) as GLenum,
+            kind: if query(location, gl::VERTEX_ATTRIB_ARRAY_LONG) != 0 {
+                AttributeKind::Double
+            } else if query(location, gl::VERTEX_ATTRIB_ARRAY_INTEGER) != 0 {
+                AttributeKind::Integer
+            } else {
+                AttributeKind::Float
+            },
+        })
+        .collect()
+}
+
+/// The number of consecutive locations an attribute of `gl_type` occupies, the
+/// components read from each, and how the shader interprets them.
+fn attribute_slots(gl_type: GLenum) -> Option<(u32, i32, AttributeKind)> {
+    Some(match gl_type {
+        gl::FLOAT => (1, 1, AttributeKind::Float),
+        gl::FLOAT_VEC2 => (1, 2, AttributeKind::Float),
+        gl::FLOAT_VEC3 => (1, 3, AttributeKind::Float),
+        gl::FLOAT_VEC4 => (1, 4, AttributeKind::Float),
+        gl::FLOAT_MAT2 => (2, 2, AttributeKind::Float),
+        gl::FLOAT_MAT3 => (3, 3, AttributeKind::Float),
+        gl::FLOAT_MAT4 => (4, 4, AttributeKind::Float),
+        gl::FLOAT_MAT2x3 => (2, 3, AttributeKind::Float),
+        gl::FLOAT_MAT2x4 => (2, 4, AttributeKind::Float),
+        gl::FLOAT_MAT3x2 => (3, 2, AttributeKind::Float),
+        gl::FLOAT_MAT3x4 => (3, 4, AttributeKind::Float),
+        gl::FLOAT_MAT4x2 => (4, 2, AttributeKind::Float),
+        gl::FLOAT_MAT4x3 => (4, 3, AttributeKind::Float),
+        gl::INT | gl::UNSIGNED_INT => (1, 1, AttributeKind::Integer),
+        gl::INT_VEC2 | gl::UNSIGNED_INT_VEC2 => (1, 2, AttributeKind::Integer),
+        gl::INT_VEC3 | gl::UNSIGNED_INT_VEC3 => (1, 3, AttributeKind::Integer),
+        gl::INT_VEC4 | gl::UNSIGNED_INT_VEC4 => (1, 4, AttributeKind::Integer),
+        gl::DOUBLE => (1, 1, AttributeKind::Double),
+        gl::DOUBLE_VEC2 => (1, 2, AttributeKind::Double),
+        gl::DOUBLE_VEC3 => (1, 3, AttributeKind::Double),
+        gl::DOUBLE_VEC4 => (1, 4, AttributeKind::Double),
+        _ => return None,
+    })
+}
+
+/// Compare the enabled arrays of a VAO against the active attributes of a program.
+///
+/// Providing fewer components than the shader reads is allowed, GL fills in the
+/// rest from `(0, 0, 0, 1)`.
+pub fn compare(vao_attributes: &[VertexArrayAttribute], interface: &ProgramInterface) -> Vec<AttributeMismatch> {
+    let mut mismatches = vec![];
+    let mut read_locations = vec![];
+
+    // Built-ins like gl_VertexID have no location
+    for attribute in interface.attributes.iter().filter(|attribute| attribute.location >= 0) {
+        let (slots, components, kind) = match attribute_slots(attribute.gl_type) {
+            Some(slots) => slots,
+            None => {
+                println!("WARNING: cannot validate attribute \"{}\" of type {}", attribute.name, type_name(attribute.gl_type));
+                continue;
+            },
+        };
+        let first_location = attribute.location as u32;
+        let slot_count = slots * attribute.array_size.max(1) as u32;
+        for location in first_location..first_location + slot_count {
+            read_locations.push(location);
+            let name = attribute.name.clone();
+            match vao_attributes.iter().find(|vao_attribute| vao_attribute.location == location) {
+                None => mismatches.push(AttributeMismatch::NotEnabled { location, name }),
+                Some(vao_attribute) if vao_attribute.kind != kind => {
+                    mismatches.push(AttributeMismatch::Kind { location, name, vao: vao_attribute.kind, shader: kind });
+                },
+                Some(vao_attribute) if vao_attribute.components > components => {
+                    mismatches.push(AttributeMismatch::ComponentCount {
+                        location, name, vao: vao_attribute.components, shader: components,
+                    });
+                },
+                Some(_) => {},
+            }
+        }
+    }
+
+    for vao_attribute in vao_attributes {
+        if !read_locations.contains(&vao_attribute.location) {
+            mismatches.push(AttributeMismatch::NotInShader { location: vao_attribute.location });
+        }
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shader::reflection::Attribute;
+
+    use super::*;
+
+    fn interface() -> ProgramInterface {
+        let attribute = |name: &str, gl_type, array_size, location| Attribute {
+            name: name.to_string(), gl_type, array_size, location,
+        };
+        ProgramInterface {
+            attributes: vec![
+                attribute("position", gl::FLOAT_VEC3, 1, 0),
+                attribute("model", gl::FLOAT_MAT4, 1, 1),
+                attribute("weights[0]", gl::FLOAT, 2, 5),
+                attribute("joints", gl::UNSIGNED_INT_VEC4, 1, 7),
+                attribute("gl_VertexID", gl::INT, 1, -1),
+            ],
+            ..ProgramInterface::default()
+        }
+    }
+
+    fn array(location: u32, components: i32, kind: AttributeKind) -> VertexArrayAttribute {
+        let gl_type = if kind == AttributeKind::Integer { gl::UNSIGNED_INT } else { gl::FLOAT };
+        VertexArrayAttribute { location, components, gl_type, kind }
+    }
+
+    #[test]
+    fn matrices_and_arrays_span_locations() {
+        let mut vao = vec![array(0, 3, AttributeKind::Float)];
+        vao.extend((1..5).map(|location| array(location, 4, AttributeKind::Float)));
+        vao.extend([array(5, 1, AttributeKind::Float), array(6, 1, AttributeKind::Float), array(7, 4, AttributeKind::Integer)]);
+        // gl_VertexID has no location, so nothing needs to feed it
+        assert!(compare(&vao, &interface()).is_empty());
+
+        // GL fills in missing components, so fewer than the shader reads is fine
+        vao[0].components = 2;
+        assert!(compare(&vao, &interface()).is_empty());
+    }
+
+    #[test]
+    fn reports_every_kind_of_mismatch() {
+        let vao = vec![
+            array(0, 4, AttributeKind::Float),
+            array(1, 4, AttributeKind::Float),
+            array(2, 4, AttributeKind::Float),
+            array(4, 4, AttributeKind::Float),
+            array(5, 1, AttributeKind::Float),
+            array(6, 1, AttributeKind::Float),
+            array(7, 4, AttributeKind::Float),
+            array(9, 2, AttributeKind::Float),
+        ];
+        let mismatches = compare(&vao, &interface());
+        assert_eq!(mismatches.len(), 4, "{:?}", mismatches);
+        assert!(matches!(
+            &mismatches[0],
+            AttributeMismatch::ComponentCount { location: 0, name, vao: 4, shader: 3 } if name == "position"
+        ));
+        // The third column of the matrix
+        assert!(matches!(&mismatches[1], AttributeMismatch::NotEnabled { location: 3, name } if name == "model"));
+        assert!(matches!(
+            &mismatches[2],
+            AttributeMismatch::Kind { location: 7, vao: AttributeKind::Float, shader: AttributeKind::Integer, .. }
+        ));
+        assert!(matches!(mismatches[3], AttributeMismatch::NotInShader { location: 9 }));
+    }
+}
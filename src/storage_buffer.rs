@@ -0,0 +1,95 @@
+use std::marker::PhantomData;
+
+use crate::gl_object::Buffer;
+use crate::layout::{self, BlockLayout, LayoutRule};
+
+/// A shader storage buffer holding an array of `T`, laid out according to std430.
+///
+/// Bind it to a block with a single runtime-sized array, such as
+/// `layout(std430, binding = N) buffer Particles { Particle particles[]; };`,
+/// with `Shader::bind_storage_buffer`, which also checks `T` against the block.
+pub struct StorageBuffer<T: BlockLayout> {
+    buffer: Buffer,
+    len: usize,
+    _contents: PhantomData<T>,
+}
+
+fn stride<T: BlockLayout>() -> usize {
+    layout::element_stride::<T>(LayoutRule::Std430)
+}
+
+fn to_bytes<T: BlockLayout>(values: &[T]) -> Vec<u8> {
+    let stride = stride::<T>();
+    let mut bytes = vec![0u8; values.len() * stride];
+    for (index, value) in values.iter().enumerate() {
+        value.write(LayoutRule::Std430, &mut bytes[index * stride..]);
+    }
+    bytes
+}
+
+impl<T: BlockLayout> StorageBuffer<T> {
+    /// A buffer initialized with `values`.
+    pub unsafe fn new(values: &[T]) -> StorageBuffer<T> {
+        let buffer = Buffer::new();
+        let bytes = to_bytes(values);
+        // Some drivers reject zero-sized buffers, so always allocate at least one element
+        let size = bytes.len().max(stride::<T>());
+        gl::NamedBufferData(buffer.id(), size as isize, std::ptr::null(), gl::DYNAMIC_DRAW);
+        gl::NamedBufferSubData(buffer.id(), 0, bytes.len() as isize, bytes.as_ptr() as *const _);
+        StorageBuffer { buffer, len: values.len(), _contents: PhantomData }
+    }
+
+    /// A buffer of `len` zeroed elements, e.g. for the output of a compute shader.
+    pub unsafe fn zeroed(len: usize) -> StorageBuffer<T> {
+        let buffer = Buffer::new();
+        let bytes = vec![0u8; len.max(1) * stride::<T>()];
+        gl::NamedBufferData(buffer.id(), bytes.len() as isize, bytes.as_ptr() as *const _, gl::DYNAMIC_DRAW);
+        StorageBuffer { buffer, len, _contents: PhantomData }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.buffer.id()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Overwrite the elements starting at index `first`. Panics if they don't fit.
+    pub unsafe fn write(&self, first: usize, values: &[T]) {
+        assert!(first + values.len() <= self.len, "write of {} elements at {} overflows buffer of {}", values.len(), first, self.len);
+        let bytes = to_bytes(values);
+        gl::NamedBufferSubData(self.buffer.id(), (first * stride::<T>()) as isize, bytes.len() as isize, bytes.as_ptr() as *const _);
+    }
+
+    /// Copy the whole buffer back to the CPU. After GPU writes, issue a
+    /// `gl::BUFFER_UPDATE_BARRIER_BIT` barrier first; `ComputeShader::dispatch` does.
+    pub unsafe fn read(&self) -> Vec<T> {
+        let stride = stride::<T>();
+        let mut bytes = vec![0u8; self.len * stride];
+        gl::GetNamedBufferSubData(self.buffer.id(), 0, bytes.len() as isize, bytes.as_mut_ptr() as *mut _);
+        bytes.chunks_exact(stride)
+            .map(|element| T::read(LayoutRule::Std430, element))
+            .collect()
+    }
+
+    /// Change the number of elements, keeping the first `min(len, new_len)` and zeroing
+    /// any new ones. This reallocates, so the buffer must be bound again afterwards.
+    pub unsafe fn resize(&mut self, len: usize) {
+        let resized = StorageBuffer::<T>::zeroed(len);
+        let kept = self.len.min(len) * stride::<T>();
+        if kept > 0 {
+            gl::CopyNamedBufferSubData(self.buffer.id(), resized.buffer.id(), 0, 0, kept as isize);
+        }
+        *self = resized;
+    }
+
+    /// Bind to a shader storage binding point directly, without checking any block.
+    pub unsafe fn bind(&self, binding: u32) {
+        gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, binding, self.buffer.id());
+    }
+}
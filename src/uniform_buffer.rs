@@ -0,0 +1,39 @@
+use std::marker::PhantomData;
+
+use crate::gl_object::Buffer;
+use crate::layout::{self, BlockLayout, LayoutRule};
+
+/// A uniform buffer holding one `T`, laid out according to std140.
+///
+/// Bind it to a `layout(std140, binding = N) uniform Block { ... };` with
+/// `Shader::bind_uniform_buffer`, which also checks `T` against the block.
+/// Shared parameters such as the camera only need to be uploaded once per frame,
+/// no matter how many programs read them.
+pub struct UniformBuffer<T: BlockLayout> {
+    buffer: Buffer,
+    _contents: PhantomData<T>,
+}
+
+impl<T: BlockLayout> UniformBuffer<T> {
+    pub unsafe fn new(value: &T) -> UniformBuffer<T> {
+        let buffer = Buffer::new();
+        let bytes = layout::to_bytes(LayoutRule::Std140, value);
+        gl::NamedBufferData(buffer.id(), bytes.len() as isize, bytes.as_ptr() as *const _, gl::DYNAMIC_DRAW);
+        UniformBuffer { buffer, _contents: PhantomData }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.buffer.id()
+    }
+
+    /// Replace the contents of the buffer with `value`.
+    pub unsafe fn update(&self, value: &T) {
+        let bytes = layout::to_bytes(LayoutRule::Std140, value);
+        gl::NamedBufferSubData(self.buffer.id(), 0, bytes.len() as isize, bytes.as_ptr() as *const _);
+    }
+
+    /// Bind to a uniform buffer binding point directly, without checking any block.
+    pub unsafe fn bind(&self, binding: u32) {
+        gl::BindBufferBase(gl::UNIFORM_BUFFER, binding, self.buffer.id());
+    }
+}
@@ -0,0 +1,2 @@
+#include "a.glsl"
+float b() { return 2.0; }
@@ -0,0 +1,2 @@
+#pragma once
+const float common_value = 1.0;
@@ -0,0 +1,2 @@
+#include "common.glsl"
+float left() { return common_value; }
@@ -0,0 +1,4 @@
+#version 450 core
+#include "left.glsl"
+#include "right.glsl"
+void main() {}
@@ -0,0 +1,2 @@
+#include "common.glsl"
+float right() { return common_value; }
@@ -0,0 +1 @@
+// only_in_dir.glsl in the include directory
@@ -0,0 +1 @@
+// shared.glsl in the include directory
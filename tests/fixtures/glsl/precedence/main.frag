@@ -0,0 +1,3 @@
+#version 450 core
+#include "shared.glsl"
+#include "only_in_dir.glsl"
@@ -0,0 +1 @@
+// shared.glsl next to main.frag